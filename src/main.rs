@@ -1,21 +1,177 @@
-use clap::{Parser, Subcommand};
+use chrono::{DateTime, Duration, Local, NaiveDate};
+use clap::{Parser, Subcommand, ValueEnum};
 use colored::Colorize;
+use regex::RegexBuilder;
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs;    
 use std::io;   
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering as AtomicOrdering};
+use std::sync::{Mutex, MutexGuard, TryLockError};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
-
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 enum TaskStatus {
     Todo,  
     Done,  
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+static NO_EMOJI: AtomicBool = AtomicBool::new(false);
+
+fn emoji(symbol: &'static str) -> &'static str {
+    if !NO_EMOJI.load(AtomicOrdering::Relaxed) {
+        return symbol;
+    }
+
+    match symbol {
+        "✓" => "OK",
+        "✗" => "ERR",
+        "⚠" => "!",
+        "☐" => "[ ]",
+        "☑" => "[x]",
+        "🎉" => "\\o/",
+        "★" => "*",
+        "⏳" => "~",
+        "☁" => "?",
+        other => other,
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+enum Priority {
+    Low,
+    Medium,
+    High,
+}
+
+impl Priority {
+
+    fn symbol(&self) -> &'static str {
+        match self {
+            Priority::Low => "!",
+            Priority::Medium => "!!",
+            Priority::High => "!!!",
+        }
+    }
+
+    fn marker(&self) -> colored::ColoredString {
+        match self {
+            Priority::Low => self.symbol().bright_blue(),
+            Priority::Medium => self.symbol().yellow(),
+            Priority::High => self.symbol().red().bold(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 struct Task {
     id: usize,               
     description: String,     
     status: TaskStatus,       
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    due: Option<NaiveDate>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    prev_descriptions: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    completed_at: Option<DateTime<Local>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    priority: Option<Priority>,
+    #[serde(default = "default_weight", skip_serializing_if = "is_default_weight")]
+    weight: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    source: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    start: Option<NaiveDate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    created_at: Option<DateTime<Local>>,
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    flags: BTreeSet<String>,
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    tags: BTreeSet<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    color: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    updated_at: Option<DateTime<Local>>,
+    // Set when `auto-prioritize` chose the priority, so it never overrides a manual one.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    auto_priority: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    estimate_mins: Option<u32>,
+}
+
+const MAX_RENAME_HISTORY: usize = 10;
+
+const KNOWN_FLAGS: [(&str, &str); 3] = [("starred", "★"), ("waiting", "⏳"), ("someday", "☁")];
+
+fn flag_glyph(flag: &str) -> Option<&'static str> {
+    KNOWN_FLAGS
+        .iter()
+        .find(|(name, _)| *name == flag)
+        .map(|(_, glyph)| emoji(glyph))
+}
+
+fn parse_flag(input: &str) -> Result<String, String> {
+    let flag = input.trim().to_lowercase();
+
+    if flag.is_empty() || flag.chars().any(|c| c.is_whitespace() || c == ',') {
+        return Err(format!("invalid flag '{}' (use a single word like starred)", input));
+    }
+
+    Ok(flag)
+}
+
+fn parse_color(input: &str) -> Result<String, String> {
+    let color = input.trim().to_lowercase();
+
+    color
+        .parse::<colored::Color>()
+        .map(|_| color.clone())
+        .map_err(|_| format!("unknown color '{}' (try red, green, bright blue, ...)", input))
+}
+
+fn parse_tag(input: &str) -> Result<String, String> {
+    let tag = input.trim().trim_start_matches('#').to_lowercase();
+
+    if tag.is_empty() || tag.chars().any(|c| c.is_whitespace() || c == ',') {
+        return Err(format!("invalid tag '{}' (use a single word like work)", input));
+    }
+
+    Ok(tag)
+}
+
+fn default_weight() -> f32 {
+    1.0
+}
+
+fn is_default_weight(weight: &f32) -> bool {
+    *weight == default_weight()
+}
+
+fn format_minutes(minutes: u64) -> String {
+    match (minutes / 60, minutes % 60) {
+        (0, m) => format!("{}m", m),
+        (h, 0) => format!("{}h", h),
+        (h, m) => format!("{}h {}m", h, m),
+    }
+}
+
+fn parse_weight(input: &str) -> Result<f32, String> {
+    let weight: f32 = input
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid weight '{}'", input))?;
+
+    if !weight.is_finite() || weight <= 0.0 {
+        return Err(format!("weight must be a positive number, got '{}'", input));
+    }
+
+    Ok(weight)
 }
 
 impl Task {
@@ -25,288 +181,4113 @@ impl Task {
             id,
             description,
             status: TaskStatus::Todo,  
+            due: None,
+            prev_descriptions: Vec::new(),
+            completed_at: None,
+            priority: None,
+            weight: default_weight(),
+            source: None,
+            start: None,
+            created_at: Some(Local::now()),
+            flags: BTreeSet::new(),
+            tags: BTreeSet::new(),
+            color: None,
+            updated_at: None,
+            auto_priority: false,
+            estimate_mins: None,
         }
     }
 
+    fn is_overdue(&self, today: NaiveDate) -> bool {
+        self.status == TaskStatus::Todo && self.due.is_some_and(|due| due < today)
+    }
+
+    fn is_deferred(&self, today: NaiveDate) -> bool {
+        self.status == TaskStatus::Todo && self.start.is_some_and(|start| start > today)
+    }
+
     fn mark_done(&mut self) {
+        if self.status != TaskStatus::Done {
+            self.completed_at = Some(Local::now());
+        }
         self.status = TaskStatus::Done;
     }
 
     fn mark_todo(&mut self) {
         self.status = TaskStatus::Todo;
+        self.completed_at = None;
+    }
+
+    fn rename(&mut self, description: String) {
+        let previous = std::mem::replace(&mut self.description, description);
+        self.prev_descriptions.push(previous);
+
+        if self.prev_descriptions.len() > MAX_RENAME_HISTORY {
+            let excess = self.prev_descriptions.len() - MAX_RENAME_HISTORY;
+            self.prev_descriptions.drain(..excess);
+        }
     }
   
-    fn display(&self) {
+    // Per-task color, then the first tag with a configured color, then none (status colors).
+    fn resolve_color(&self, config: &Config) -> Option<colored::Color> {
+        self.color
+            .iter()
+            .chain(self.tags.iter().filter_map(|tag| config.tag_colors.get(tag)))
+            .find_map(|name| name.parse().ok())
+    }
+
+    fn display(&self, config: &Config) {
+        println!("{}", self.render(config, config.wrap_width()));
+    }
+
+    fn render(&self, config: &Config, wrap_width: Option<usize>) -> String {
         let status_symbol = match self.status {
-            TaskStatus::Todo => "☐".bright_red(),    
-            TaskStatus::Done => "☑".bright_green(),  
+            TaskStatus::Todo => emoji("☐").bright_red(),    
+            TaskStatus::Done => emoji("☑").bright_green(),  
         };
 
-        let description = match self.status {
-            TaskStatus::Todo => self.description.bright_white(),                     
-            TaskStatus::Done => self.description.bright_black().strikethrough(),      
+        let color = self.resolve_color(config);
+        let style_description = |text: &str| match (self.status.clone(), color) {
+            (TaskStatus::Todo, Some(color)) => text.color(color),
+            (TaskStatus::Done, Some(color)) => text.color(color).strikethrough(),
+            (TaskStatus::Todo, None) => text.bright_white(),                     
+            (TaskStatus::Done, None) => text.bright_black().strikethrough(),      
         };
 
-       
-        println!("[{}] {} {}", 
-                 self.id.to_string().bright_cyan(),  
-                 status_symbol,                       
-                 description);                        
-    }
-}
+        let today = Local::now().date_naive();
+        let mut notes: Vec<(String, bool)> = self.tags
+            .iter()
+            .map(|tag| (format!("#{}", tag), false))
+            .collect();
+        if self.is_deferred(today) {
+            notes.extend(self.start.map(|start| (format!("(starts {})", start), false)));
+        }
+        if let Some(due) = self.due {
+            notes.push((format!("(due {})", due), self.is_overdue(today)));
+        }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct TodoList {
-    tasks: Vec<Task>,    
-    next_id: usize,      
-}
+        let suffix = (!notes.is_empty()).then(|| {
+            notes.iter().map(|(text, _)| text.as_str()).collect::<Vec<_>>().join(" ")
+        });
+        let styled_suffix = notes
+            .iter()
+            .map(|(text, alert)| if *alert { text.red() } else { text.bright_black() }.to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
 
-impl TodoList {
-    
-    fn new() -> Self {
-        TodoList {
-            tasks: Vec::new(),   
-            next_id: 1,          
+        let priority = match self.priority {
+            Some(priority) => format!("{} ", priority.marker()),
+            None => String::new(),
+        };
+
+        let glyphs: String = self.flags
+            .iter()
+            .filter_map(|flag| flag_glyph(flag))
+            .map(|glyph| format!("{} ", glyph.yellow()))
+            .collect();
+
+        let prefix_width = format!("{} {} ", config.id_style.plain(self.id), emoji("☐")).width()
+            + self.priority.map_or(0, |p| p.symbol().width() + 1)
+            + visible_width(&glyphs);
+
+        let mut suffix_on_own_line = None;
+        let lines: Vec<(usize, String)> = match wrap_width {
+            Some(width) => {
+                let indent = config.wrap_indent.unwrap_or(prefix_width);
+                let lines: Vec<(usize, String)> = wrap_words(&self.description,
+                                                             width.saturating_sub(prefix_width),
+                                                             width.saturating_sub(indent))
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, line)| (if i == 0 { 0 } else { indent }, line))
+                    .collect();
+
+                let last_width = match lines.as_slice() {
+                    [(_, only)] => prefix_width + only.width(),
+                    [.., (indent, last)] => indent + last.width(),
+                    [] => prefix_width,
+                };
+                if suffix.as_ref().is_some_and(|suffix| last_width + 1 + suffix.width() > width) {
+                    suffix_on_own_line = Some(indent);
+                }
+                lines
+            }
+            None => vec![(0, self.description.clone())],
+        };
+
+        let mut rendered = format!("{} {} {}{}", 
+                                   config.id_style.styled(self.id),  
+                                   status_symbol,                       
+                                   priority,
+                                   glyphs);
+        for (i, (indent, line)) in lines.iter().enumerate() {
+            if i > 0 {
+                rendered.push('\n');
+            }
+            rendered.push_str(&" ".repeat(*indent));
+            rendered.push_str(&style_description(line).to_string());
+        }
+        if suffix.is_some() {
+            match suffix_on_own_line {
+                Some(indent) => {
+                    rendered.push('\n');
+                    rendered.push_str(&" ".repeat(indent));
+                }
+                None => rendered.push(' '),
+            }
+            rendered.push_str(&styled_suffix);
         }
-    }
 
-    fn get_file_path() -> String {
-        "tasks.json".to_string()
+        rendered
     }
 
-    fn load() -> io::Result<Self> {
-        let path = Self::get_file_path();
+    fn display_details(&self, config: &Config) {
+        let status = match self.status {
+            TaskStatus::Todo => "Todo".bright_red(),
+            TaskStatus::Done => "Done".bright_green(),
+        };
 
-        if !std::path::Path::new(&path).exists() {
-            return Ok(Self::new());
+        println!("\n{} {}\n", 
+                 format!("Task {}:", config.id_style.in_message(self.id)).bold().bright_blue(),
+                 self.description.bright_white());
+        println!("  {:<10} {}", "Status:".bold(), status);
+
+        if let Some(priority) = self.priority {
+            let auto = if self.auto_priority { " (auto)" } else { "" };
+            println!("  {:<10} {:?}{}", "Priority:".bold(), priority, auto.bright_black());
         }
 
-        let contents = fs::read_to_string(path)?;
-        
-        let todo_list: TodoList = serde_json::from_str(&contents)
-            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if let Some(start) = self.start {
+            println!("  {:<10} {}", "Starts:".bold(), start);
+        }
 
-        Ok(todo_list)
-    }
+        if let Some(due) = self.due {
+            println!("  {:<10} {}", "Due:".bold(), due);
+        }
 
-    fn save(&self) -> io::Result<()> {
-        let path = Self::get_file_path();
-        
-        let json = serde_json::to_string_pretty(self)
-            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if !is_default_weight(&self.weight) {
+            println!("  {:<10} {}", "Weight:".bold(), self.weight);
+        }
 
-        fs::write(path, json)?;
-        
-        Ok(())  
-    }
+        if let Some(minutes) = self.estimate_mins {
+            println!("  {:<10} {}", "Estimate:".bold(), format_minutes(u64::from(minutes)));
+        }
 
-    fn add_task(&mut self, description: String) -> usize {
-        let id = self.next_id;                           
-        let task = Task::new(id, description);          
-        self.tasks.push(task);                           
-        self.next_id += 1;                              
-        id                                              
-    }
+        if let Some(source) = &self.source {
+            println!("  {:<10} {}", "Source:".bold(), source);
+        }
 
-    fn mark_done(&mut self, id: usize) -> bool {
-        
-        if let Some(task) = self.tasks.iter_mut().find(|t| t.id == id) {
-            task.mark_done();
-            true
-        } else {
-            false  
+        if !self.flags.is_empty() {
+            let flags: Vec<&str> = self.flags.iter().map(String::as_str).collect();
+            println!("  {:<10} {}", "Flags:".bold(), flags.join(", "));
         }
-    }
 
-    fn mark_todo(&mut self, id: usize) -> bool {
-        if let Some(task) = self.tasks.iter_mut().find(|t| t.id == id) {
-            task.mark_todo();
-            true
-        } else {
-            false
+        if !self.tags.is_empty() {
+            let tags: Vec<String> = self.tags.iter().map(|tag| format!("#{}", tag)).collect();
+            println!("  {:<10} {}", "Tags:".bold(), tags.join(" "));
         }
-    }
 
-    fn remove_task(&mut self, id: usize) -> bool {
+        if let Some(color) = &self.color {
+            println!("  {:<10} {}", "Color:".bold(), color);
+        }
 
-        if let Some(pos) = self.tasks.iter().position(|t| t.id == id) {
-            self.tasks.remove(pos);  
-            true
-        } else {
-            false
+        if let Some(created_at) = self.created_at {
+            println!("  {:<10} {}", "Created:".bold(), created_at.format("%Y-%m-%d %H:%M"));
         }
-    }
 
-    fn list_all(&self) {
-        if self.tasks.is_empty() {
-            println!("{}", "No tasks yet! Add one with: todo add \"your task\"".yellow());
-            return;
+        if let Some(completed_at) = self.completed_at {
+            println!("  {:<10} {}", "Done:".bold(), completed_at.format("%Y-%m-%d %H:%M"));
         }
 
-        println!("\n{}\n", "All Tasks:".bold().bright_blue());
-        for task in &self.tasks {
-            task.display();
+        if !self.prev_descriptions.is_empty() {
+            println!("  {}", "Previously:".bold());
+            for previous in self.prev_descriptions.iter().rev() {
+                println!("    - {}", previous.bright_black());
+            }
         }
         println!();
     }
+}
 
-    fn list_todo(&self) {
-        let todos: Vec<&Task> = self.tasks
-            .iter()
-            .filter(|t| t.status == TaskStatus::Todo)
-            .collect();
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum SortKey {
+    Id,
+    Due,
+    Priority,
+}
 
-        if todos.is_empty() {
-            println!("{}", "No pending tasks! 🎉".green().bold());
-            return;
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+enum DonePosition {
+    #[default]
+    Interleaved,
+    Bottom,
+    Hidden,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum CountKey {
+    Tag,
+    Priority,
+    Status,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ListView {
+    All,
+    Todo,
+    Done,
+    DueWithin(Duration),
+}
+
+impl ListView {
+
+    fn title(&self) -> colored::ColoredString {
+        match self {
+            ListView::All => "All Tasks:".bold().bright_blue(),
+            ListView::Todo => "Pending Tasks:".bold().bright_red(),
+            ListView::Done => "Completed Tasks:".bold().bright_green(),
+            ListView::DueWithin(within) => {
+                format!("Due Within {} Day(s):", within.num_days()).bold().bright_yellow()
+            }
         }
+    }
 
-        println!("\n{}\n", "Pending Tasks:".bold().bright_red());
-        for task in todos {
-            task.display();
+    fn empty_message(&self) -> colored::ColoredString {
+        match self {
+            ListView::All => "No tasks yet! Add one with: todo add \"your task\"".yellow(),
+            ListView::Todo => format!("No pending tasks! {}", emoji("🎉")).green().bold(),
+            ListView::Done => "No completed tasks yet.".yellow(),
+            ListView::DueWithin(_) => "Nothing due in that window.".green().bold(),
         }
-        println!();
     }
-  
-    fn list_done(&self) {
-        let done: Vec<&Task> = self.tasks
-            .iter()
-            .filter(|t| t.status == TaskStatus::Done)
-            .collect();
+}
 
-        if done.is_empty() {
-            println!("{}", "No completed tasks yet.".yellow());
-            return;
+fn wrap_words(text: &str, first_width: usize, rest_width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    let mut limit = first_width.max(1);
+
+    for word in text.split_whitespace() {
+        if !current.is_empty() && current.width() + 1 + word.width() > limit {
+            lines.push(std::mem::take(&mut current));
+            limit = rest_width.max(1);
         }
 
-        println!("\n{}\n", "Completed Tasks:".bold().bright_green());
-        for task in done {
-            task.display();
+        if !current.is_empty() {
+            current.push(' ');
         }
-        println!();
-    }
 
-    fn clear_done(&mut self) -> usize {
-        let initial_count = self.tasks.len();
-       
-        self.tasks.retain(|t| t.status == TaskStatus::Todo);
-        
-        initial_count - self.tasks.len()  
+        // A word wider than a whole line (e.g. CJK text without spaces) is broken mid-word.
+        for c in word.chars() {
+            if !current.is_empty() && current.width() + c.width().unwrap_or(0) > limit {
+                lines.push(std::mem::take(&mut current));
+                limit = rest_width.max(1);
+            }
+            current.push(c);
+        }
     }
+
+    lines.push(current);
+    lines
 }
 
-#[derive(Parser)]
-#[command(name = "todo")]
-#[command(about = "Manage your tasks from the command line", long_about = None)]
-#[command(version)]
-struct Cli {
-    #[command(subcommand)]
-    command: Commands,
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum InputFormat {
+    Text,
+    Markdown,
+    Csv,
+    Json,
 }
 
-#[derive(Subcommand)]
-enum Commands {
-    
-    Add {
-       
-        description: String,
-    },
+impl InputFormat {
 
-    List {
-       
-        #[arg(short, long)]
-        todo: bool,
+    fn from_path(path: &str) -> Option<Self> {
+        let extension = std::path::Path::new(path).extension()?.to_str()?.to_lowercase();
 
-        #[arg(short, long)]
-        done: bool,
-    },
+        match extension.as_str() {
+            "txt" => Some(InputFormat::Text),
+            "md" | "markdown" => Some(InputFormat::Markdown),
+            "csv" => Some(InputFormat::Csv),
+            "json" => Some(InputFormat::Json),
+            _ => None,
+        }
+    }
+}
 
-    Done {
-      
-        id: usize,
-    },
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum MergeStrategy {
+    NewIds,
+    SkipDuplicates,
+    Replace,
+}
 
-    Undone {
+#[derive(Debug, Default)]
+struct ImportReport {
+    added: Vec<usize>,
+    skipped: usize,
+    replaced: Vec<usize>,
+}
 
-        id: usize,
-    },
+fn parse_csv_line(line: &str) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
 
-    Remove {
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
 
-        id: usize,
-    },
+    if in_quotes {
+        return Err("unterminated quoted field".to_string());
+    }
 
-    Clear,
+    fields.push(field);
+    Ok(fields)
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-
-    let cli = Cli::parse();
-    
-let mut todo_list = TodoList::load().unwrap_or_else(|_| TodoList::new());
+fn parse_import(contents: &str, format: InputFormat) -> (Vec<Task>, Vec<String>) {
+    let mut tasks = Vec::new();
+    let mut errors = Vec::new();
 
-match cli.command {
-    Commands::Add { description } => {
-        let id = todo_list.add_task(description.clone());
-        todo_list.save()?;
-        println!("{} Task #{} added: {}", 
-                 "✓".green().bold(), 
-                 id.to_string().cyan().bold(),
-                 description.bright_white());
-    }
-    Commands::List { todo, done } => {
-        if todo {
-            todo_list.list_todo();
-        } else if done {
-            todo_list.list_done();
-        } else {
-            todo_list.list_all();
+    match format {
+        InputFormat::Text => {
+            for line in contents.lines().map(str::trim).filter(|l| !l.is_empty()) {
+                tasks.push(Task::new(0, line.to_string()));
+            }
         }
-    }
-    Commands::Done { id } => {
-        if todo_list.mark_done(id) {
-            todo_list.save()?;
-            println!("{} Task #{} marked as done!", 
-                     "✓".green().bold(), 
-                     id.to_string().cyan().bold());
-        } else {
-            eprintln!("{} Task #{} not found.", 
-                     "✗".red().bold(), 
-                     id.to_string().cyan());
+        InputFormat::Markdown => {
+            for line in contents.lines().map(str::trim) {
+                let Some(item) = line.strip_prefix("- ").or_else(|| line.strip_prefix("* ")) else {
+                    continue;
+                };
+
+                let (done, description) = if let Some(rest) = item.strip_prefix("[ ] ") {
+                    (false, rest)
+                } else if let Some(rest) = item.strip_prefix("[x] ").or_else(|| item.strip_prefix("[X] ")) {
+                    (true, rest)
+                } else {
+                    (false, item)
+                };
+
+                if description.trim().is_empty() {
+                    continue;
+                }
+
+                let mut task = Task::new(0, description.trim().to_string());
+                if done {
+                    task.mark_done();
+                }
+                tasks.push(task);
+            }
         }
-    }
-    Commands::Undone { id } => {
-        if todo_list.mark_todo(id) {
-            todo_list.save()?;
-            println!("{} Task #{} marked as todo.", 
-                     "✓".green().bold(), 
-                     id.to_string().cyan().bold());
-        } else {
-            eprintln!("{} Task #{} not found.", 
-                     "✗".red().bold(), 
-                     id.to_string().cyan());
+        InputFormat::Csv => {
+            let mut lines = contents.lines().enumerate().filter(|(_, l)| !l.trim().is_empty());
+
+            let header = match lines.next().map(|(_, l)| parse_csv_line(l)) {
+                Some(Ok(header)) => header.iter().map(|h| h.trim().to_lowercase()).collect::<Vec<_>>(),
+                Some(Err(e)) => {
+                    errors.push(format!("line 1: {}", e));
+                    return (tasks, errors);
+                }
+                None => return (tasks, errors),
+            };
+
+            let Some(description_column) = header.iter().position(|h| h == "description") else {
+                errors.push("line 1: missing a 'description' column".to_string());
+                return (tasks, errors);
+            };
+
+            for (index, line) in lines {
+                let line_number = index + 1;
+                let fields = match parse_csv_line(line) {
+                    Ok(fields) => fields,
+                    Err(e) => {
+                        errors.push(format!("line {}: {}", line_number, e));
+                        continue;
+                    }
+                };
+
+                let column = |name: &str| {
+                    header.iter()
+                        .position(|h| h == name)
+                        .and_then(|i| fields.get(i))
+                        .map(|value| value.trim())
+                        .filter(|value| !value.is_empty())
+                };
+
+                let Some(description) = fields.get(description_column).map(|d| d.trim()).filter(|d| !d.is_empty()) else {
+                    errors.push(format!("line {}: empty description", line_number));
+                    continue;
+                };
+
+                let mut task = Task::new(0, description.to_string());
+
+                match column("status").map(str::to_lowercase).as_deref() {
+                    Some("done") => task.mark_done(),
+                    Some("todo") | None => {}
+                    Some(other) => {
+                        errors.push(format!("line {}: unknown status '{}'", line_number, other));
+                        continue;
+                    }
+                }
+
+                if let Some(due) = column("due") {
+                    match parse_date(due) {
+                        Ok(due) => task.due = Some(due),
+                        Err(e) => {
+                            errors.push(format!("line {}: {}", line_number, e));
+                            continue;
+                        }
+                    }
+                }
+
+                if let Some(priority) = column("priority") {
+                    match Priority::from_str(priority, true) {
+                        Ok(priority) => task.priority = Some(priority),
+                        Err(_) => {
+                            errors.push(format!("line {}: unknown priority '{}'", line_number, priority));
+                            continue;
+                        }
+                    }
+                }
+
+                tasks.push(task);
+            }
         }
-    }
-    Commands::Remove { id } => {
-        if todo_list.remove_task(id) {
-            todo_list.save()?;
-            println!("{} Task #{} removed.", 
-                     "✓".green().bold(), 
-                     id.to_string().cyan().bold());
-        } else {
-            eprintln!("{} Task #{} not found.", 
-                     "✗".red().bold(), 
-                     id.to_string().cyan());
+        InputFormat::Json => {
+            let value: serde_json::Value = match serde_json::from_str(contents) {
+                Ok(value) => value,
+                Err(e) => {
+                    errors.push(format!("invalid JSON: {}", e));
+                    return (tasks, errors);
+                }
+            };
+
+            // Accept either a whole TodoList or a bare array of tasks.
+            let items = match value {
+                serde_json::Value::Array(items) => items,
+                serde_json::Value::Object(mut list) => match list.remove("tasks") {
+                    Some(serde_json::Value::Array(items)) => items,
+                    _ => {
+                        errors.push("expected a task list object with a 'tasks' array".to_string());
+                        return (tasks, errors);
+                    }
+                },
+                _ => {
+                    errors.push("expected a task list object or an array of tasks".to_string());
+                    return (tasks, errors);
+                }
+            };
+
+            for (index, mut item) in items.into_iter().enumerate() {
+                // Ids are reassigned on import, so they're optional here.
+                if let Some(object) = item.as_object_mut() {
+                    object.entry("id").or_insert(serde_json::json!(0));
+                    object.entry("status").or_insert(serde_json::json!("Todo"));
+                }
+
+                match serde_json::from_value::<Task>(item) {
+                    Ok(task) if task.description.trim().is_empty() => {
+                        errors.push(format!("item {}: empty description", index + 1));
+                    }
+                    Ok(task) => tasks.push(task),
+                    Err(e) => errors.push(format!("item {}: {}", index + 1, e)),
+                }
+            }
         }
     }
-    Commands::Clear => {
-        let count = todo_list.clear_done();
-        todo_list.save()?;
-        println!("{} Cleared {} completed task(s).", 
-                 "✓".green().bold(), 
-                 count.to_string().cyan().bold());
-    }
+
+    (tasks, errors)
 }
 
-Ok(())
+#[cfg(feature = "interactive")]
+fn fuzzy_matches(text: &str, query: &str) -> bool {
+    let mut haystack = text.chars().flat_map(char::to_lowercase);
+    query
+        .chars()
+        .flat_map(char::to_lowercase)
+        .all(|wanted| haystack.any(|c| c == wanted))
+}
+
+#[cfg(feature = "interactive")]
+fn pick_task(tasks: &[&Task]) -> io::Result<Option<usize>> {
+    use crossterm::event::{self, Event as TermEvent, KeyCode, KeyEventKind, KeyModifiers};
+    use crossterm::{cursor, execute, queue, style, terminal};
+    use std::io::IsTerminal;
+
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        return Err(io::Error::other("interactive mode needs a terminal"));
+    }
+
+    // Puts the terminal back however the picker exits, errors included.
+    struct RawScreen;
+
+    impl Drop for RawScreen {
+        fn drop(&mut self) {
+            let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+            let _ = terminal::disable_raw_mode();
+        }
+    }
+
+    let mut stdout = io::stdout();
+    let mut query = String::new();
+    let mut selected = 0;
+
+    terminal::enable_raw_mode()?;
+    let _screen = RawScreen;
+    execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide)?;
+
+    let picked = loop {
+        let matches: Vec<&&Task> = tasks.iter().filter(|t| fuzzy_matches(&t.description, &query)).collect();
+        selected = selected.min(matches.len().saturating_sub(1));
+
+        let (_, height) = terminal::size()?;
+        queue!(stdout, terminal::Clear(terminal::ClearType::All), cursor::MoveTo(0, 0))?;
+        queue!(stdout, style::Print(format!("{} {}", ">".bright_cyan().bold(), query)))?;
+        for (row, task) in matches.iter().take(usize::from(height.saturating_sub(1))).enumerate() {
+            let line = format!("[{}] {}", task.id, task.description);
+            let line = if row == selected { line.reversed().to_string() } else { line };
+            queue!(stdout, cursor::MoveTo(0, row as u16 + 1), style::Print(line))?;
+        }
+        stdout.flush()?;
+
+        if let TermEvent::Key(key) = event::read()? {
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            match key.code {
+                KeyCode::Enter => break matches.get(selected).map(|t| t.id),
+                KeyCode::Esc => break None,
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => break None,
+                KeyCode::Up => selected = selected.saturating_sub(1),
+                KeyCode::Down => selected += 1,
+                KeyCode::Backspace => {
+                    query.pop();
+                }
+                KeyCode::Char(c) => query.push(c),
+                _ => {}
+            }
+        }
+    };
+
+    Ok(picked)
+}
+
+#[cfg(not(feature = "interactive"))]
+fn pick_task(_tasks: &[&Task]) -> io::Result<Option<usize>> {
+    Err(io::Error::other("interactive mode isn't built in; reinstall with --features interactive"))
+}
+
+fn confirm(prompt: &str) -> io::Result<bool> {
+    print!("{} {} ", prompt, "[y/N]".bright_black());
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+// For commands that don't normally prompt: ask only when `confirm_destructive` is on.
+fn confirm_destructive(config: &Config, yes: bool, prompt: &str) -> io::Result<bool> {
+    if yes || !config.confirm_destructive {
+        return Ok(true);
+    }
+
+    confirm(prompt)
+}
+
+fn ansi_sequence_len(text: &str) -> Option<usize> {
+    let rest = text.strip_prefix("\x1b[")?;
+    let end = rest.find(|c: char| ('@'..='~').contains(&c))?;
+    Some(2 + end + 1)
+}
+
+fn visible_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut index = 0;
+
+    while index < text.len() {
+        if let Some(len) = ansi_sequence_len(&text[index..]) {
+            index += len;
+            continue;
+        }
+
+        let c = text[index..].chars().next().expect("index is on a char boundary");
+        width += unicode_width::UnicodeWidthChar::width(c).unwrap_or(0);
+        index += c.len_utf8();
+    }
+
+    width
+}
+
+fn truncate_visible(text: &str, max_width: usize) -> String {
+    if visible_width(text) <= max_width {
+        return text.to_string();
+    }
+
+    let budget = max_width.saturating_sub(1);
+    let mut truncated = String::new();
+    let mut width = 0;
+    let mut index = 0;
+    let mut styled = false;
+
+    while index < text.len() {
+        if let Some(len) = ansi_sequence_len(&text[index..]) {
+            truncated.push_str(&text[index..index + len]);
+            styled = true;
+            index += len;
+            continue;
+        }
+
+        let c = text[index..].chars().next().expect("index is on a char boundary");
+        let char_width = unicode_width::UnicodeWidthChar::width(c).unwrap_or(0);
+        if width + char_width > budget {
+            break;
+        }
+
+        truncated.push(c);
+        width += char_width;
+        index += c.len_utf8();
+    }
+
+    if max_width > 0 {
+        truncated.push('…');
+    }
+    if styled {
+        truncated.push_str("\x1b[0m");
+    }
+
+    truncated
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Columns {
+    Auto,
+    Fixed(usize),
+}
+
+fn parse_columns(input: &str) -> Result<Columns, String> {
+    match input.trim() {
+        "auto" => Ok(Columns::Auto),
+        n => match n.parse::<usize>() {
+            Ok(count) if count > 0 => Ok(Columns::Fixed(count)),
+            _ => Err(format!("invalid column count '{}' (expected auto or a positive number)", input)),
+        },
+    }
+}
+
+const COLUMN_GAP: usize = 2;
+
+// Lays cells out column by column like `ls`, or returns None when they don't fit side by side.
+fn layout_columns(cells: &[String], columns: Columns, width: Option<usize>) -> Option<Vec<String>> {
+    let widest = cells.iter().map(|cell| visible_width(cell)).max()?;
+
+    let count = match (columns, width) {
+        (Columns::Auto, Some(width)) => (width + COLUMN_GAP) / (widest + COLUMN_GAP),
+        (Columns::Auto, None) => 1,
+        (Columns::Fixed(count), _) => count,
+    }
+    .min(cells.len());
+    if count < 2 {
+        return None;
+    }
+
+    let rows = cells.len().div_ceil(count);
+    let count = cells.len().div_ceil(rows);
+    let column_widths: Vec<usize> = cells
+        .chunks(rows)
+        .map(|column| column.iter().map(|cell| visible_width(cell)).max().unwrap_or(0))
+        .collect();
+
+    let total = column_widths.iter().sum::<usize>() + COLUMN_GAP * (count - 1);
+    if width.is_some_and(|width| total > width) {
+        return None;
+    }
+
+    let lines = (0..rows)
+        .map(|row| {
+            let mut line = String::new();
+            for (column, column_width) in column_widths.iter().enumerate() {
+                let Some(cell) = cells.get(column * rows + row) else {
+                    break;
+                };
+                if column > 0 {
+                    line.push_str(&" ".repeat(COLUMN_GAP));
+                }
+                line.push_str(cell);
+                if column + 1 < count && cells.get((column + 1) * rows + row).is_some() {
+                    line.push_str(&" ".repeat(column_width - visible_width(cell)));
+                }
+            }
+            line
+        })
+        .collect();
+
+    Some(lines)
+}
+
+fn terminal_width() -> Option<usize> {
+    terminal_size::terminal_size().map(|(terminal_size::Width(width), _)| usize::from(width))
+}
+
+fn summary_line(tasks: &[&Task], today: NaiveDate, pending_total: bool) -> String {
+    let pending = || tasks.iter().filter(|t| t.status == TaskStatus::Todo);
+
+    let high = pending().filter(|t| t.priority == Some(Priority::High)).count();
+    let overdue = pending().filter(|t| t.is_overdue(today)).count();
+    let this_week = pending()
+        .filter(|t| t.due.is_some_and(|due| due >= today && due < today + Duration::days(7)))
+        .count();
+
+    let mut segments = if pending_total {
+        vec![format!("{} pending", pending().count())]
+    } else {
+        vec![format!("{} shown", tasks.len())]
+    };
+    for (count, label) in [(high, "high"), (overdue, "overdue"), (this_week, "due this week")] {
+        if count > 0 {
+            segments.push(format!("{} {}", count, label));
+        }
+    }
+
+    segments.join(" · ")
+}
+
+#[derive(Debug, Serialize)]
+struct TaskView<'a> {
+    #[serde(flatten)]
+    task: &'a Task,
+    overdue: bool,
+    days_until_due: Option<i64>,
+    age_days: Option<i64>,
+}
+
+impl<'a> TaskView<'a> {
+
+    fn new(task: &'a Task, today: NaiveDate) -> Self {
+        TaskView {
+            task,
+            overdue: task.is_overdue(today),
+            days_until_due: task.due.map(|due| (due - today).num_days()),
+            age_days: task.created_at.map(|created| (today - created.date_naive()).num_days()),
+        }
+    }
+}
+
+// 1-based page of `limit` items, plus the page count.
+fn paginate<T>(items: &[T], limit: usize, page: usize) -> (&[T], usize) {
+    let pages = items.len().div_ceil(limit).max(1);
+    let start = (page - 1).saturating_mul(limit).min(items.len());
+    let end = start.saturating_add(limit).min(items.len());
+
+    (&items[start..end], pages)
+}
+
+fn print_json<T: Serialize>(items: &[T]) -> io::Result<()> {
+    let json = serde_json::to_string(items)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    println!("{}", json);
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum ExportFormat {
+    Tsv,
+    Json,
+    Jsonl,
+    Markdown,
+}
+
+// What `export --since-file` has already sent.
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(default)]
+struct ExportState {
+    exported_at: Option<DateTime<Local>>,
+    max_id: usize,
+}
+
+impl ExportState {
+
+    fn load(path: &str) -> io::Result<Self> {
+        if !std::path::Path::new(path).exists() {
+            return Ok(Self::default());
+        }
+
+        serde_json::from_str(&fs::read_to_string(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    fn save(&self, path: &str) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(path, json)
+    }
+
+    fn is_new(&self, task: &Task) -> bool {
+        let changed_at = task.updated_at.or(task.created_at);
+
+        task.id > self.max_id
+            || self.exported_at.is_none_or(|exported_at| changed_at.is_some_and(|t| t > exported_at))
+    }
+}
+
+const TSV_COLUMNS: [&str; 15] = [
+    "id", "description", "status", "priority", "due", "start", "weight", "estimate_mins", "created_at",
+    "updated_at", "completed_at", "source", "flags", "tags", "color",
+];
+
+fn escape_tsv(field: &str) -> String {
+    let mut escaped = String::with_capacity(field.len());
+    for c in field.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn export_tasks(tasks: &[&Task], format: ExportFormat) -> serde_json::Result<String> {
+    match format {
+        ExportFormat::Json => serde_json::to_string_pretty(tasks).map(|json| json + "\n"),
+        ExportFormat::Markdown => Ok(tasks
+            .iter()
+            .map(|task| {
+                let checkbox = if task.status == TaskStatus::Done { "x" } else { " " };
+                format!("- [{}] {}\n", checkbox, task.description)
+            })
+            .collect()),
+        ExportFormat::Jsonl => tasks
+            .iter()
+            .map(|task| serde_json::to_string(task).map(|line| line + "\n"))
+            .collect(),
+        ExportFormat::Tsv => {
+            let mut out = TSV_COLUMNS.join("\t");
+            out.push('\n');
+
+            for task in tasks {
+                let fields = [
+                    task.id.to_string(),
+                    escape_tsv(&task.description),
+                    format!("{:?}", task.status),
+                    task.priority.map(|p| format!("{:?}", p).to_lowercase()).unwrap_or_default(),
+                    task.due.map(|d| d.to_string()).unwrap_or_default(),
+                    task.start.map(|d| d.to_string()).unwrap_or_default(),
+                    task.weight.to_string(),
+                    task.estimate_mins.map(|m| m.to_string()).unwrap_or_default(),
+                    task.created_at.map(|t| t.to_rfc3339()).unwrap_or_default(),
+                    task.updated_at.map(|t| t.to_rfc3339()).unwrap_or_default(),
+                    task.completed_at.map(|t| t.to_rfc3339()).unwrap_or_default(),
+                    task.source.as_deref().map(escape_tsv).unwrap_or_default(),
+                    task.flags.iter().map(String::as_str).collect::<Vec<_>>().join(","),
+                    task.tags.iter().map(String::as_str).collect::<Vec<_>>().join(","),
+                    task.color.clone().unwrap_or_default(),
+                ];
+                out.push_str(&fields.join("\t"));
+                out.push('\n');
+            }
+
+            Ok(out)
+        }
+    }
+}
+
+#[cfg(feature = "schema")]
+fn task_file_schema() -> schemars::schema::RootSchema {
+    let mut root = schemars::schema_for!(TodoList);
+
+    // `version` is added on save rather than stored on TodoList itself.
+    let version = schemars::gen::SchemaGenerator::default().subschema_for::<u32>();
+    root.schema.object().properties.insert("version".to_string(), version);
+
+    root
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct SortSpec {
+    key: SortKey,
+    descending: bool,
+}
+
+fn parse_sort_spec(input: &str) -> Result<SortSpec, String> {
+    let (name, descending) = match input.trim().strip_prefix('-') {
+        Some(name) => (name, true),
+        None => (input.trim(), false),
+    };
+
+    let key = SortKey::from_str(name, true)
+        .map_err(|_| format!("unknown sort key '{}' (expected id, due or priority)", name))?;
+
+    Ok(SortSpec { key, descending })
+}
+
+#[cfg(feature = "clipboard")]
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum CopyFormat {
+    Description,
+    Markdown,
+}
+
+#[cfg(feature = "clipboard")]
+fn copy_to_clipboard(task: &Task, format: CopyFormat) -> Result<String, arboard::Error> {
+    let text = match format {
+        CopyFormat::Description => task.description.clone(),
+        CopyFormat::Markdown => {
+            let checkbox = if task.status == TaskStatus::Done { "x" } else { " " };
+            format!("- [{}] {}", checkbox, task.description)
+        }
+    };
+
+    arboard::Clipboard::new()?.set_text(text.clone())?;
+    Ok(text)
+}
+
+#[derive(Debug, Clone, Default)]
+struct ListOptions {
+    sort: SortOrder,
+    max_width: Option<usize>,
+    source: Option<String>,
+    include_deferred: bool,
+    summary: bool,
+    pending_total: bool,
+    flag: Option<String>,
+    columns: Option<Columns>,
+    filter: Option<Filter>,
+    done_position: DonePosition,
+}
+
+impl ListOptions {
+
+    fn matches(&self, task: &Task) -> bool {
+        if !self.include_deferred && task.is_deferred(Local::now().date_naive()) {
+            return false;
+        }
+
+        if self.flag.as_ref().is_some_and(|flag| !task.flags.contains(flag)) {
+            return false;
+        }
+
+        if self.filter.as_ref().is_some_and(|filter| !filter.matches(task, Local::now().date_naive())) {
+            return false;
+        }
+
+        self.source.as_ref().is_none_or(|source| task.source.as_ref() == Some(source))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct SortOrder {
+    specs: Vec<SortSpec>,
+    undated_first: bool,
+}
+
+fn compare_due(a: Option<NaiveDate>, b: Option<NaiveDate>, undated_first: bool) -> Ordering {
+    let undated = if undated_first { Ordering::Less } else { Ordering::Greater };
+
+    match (a, b) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => undated.reverse(),
+        (None, Some(_)) => undated,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+fn compare_by_key(a: &Task, b: &Task, key: SortKey) -> Ordering {
+    match key {
+        SortKey::Id => a.id.cmp(&b.id),
+        SortKey::Due => compare_due(a.due, b.due, false),
+        // Highest priority first, unprioritized last.
+        SortKey::Priority => b.priority.cmp(&a.priority),
+    }
+}
+
+fn sort_tasks(tasks: &mut [&Task], order: &SortOrder) {
+    if order.specs.is_empty() {
+        return;
+    }
+
+    tasks.sort_by(|a, b| {
+        order.specs
+            .iter()
+            .map(|spec| {
+                // Where undated tasks go is its own setting, not flipped by `-due`.
+                if spec.key == SortKey::Due && a.due.is_some() != b.due.is_some() {
+                    return compare_due(a.due, b.due, order.undated_first);
+                }
+                // Likewise unprioritized tasks stay last under `-priority`.
+                if spec.key == SortKey::Priority && a.priority.is_some() != b.priority.is_some() {
+                    return b.priority.is_some().cmp(&a.priority.is_some());
+                }
+
+                let ordering = compare_by_key(a, b, spec.key);
+                if spec.descending { ordering.reverse() } else { ordering }
+            })
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    });
+}
+
+fn parse_duration(input: &str) -> Result<Duration, String> {
+    let trimmed = input.trim().trim_start_matches('+');
+
+    let unit_start = trimmed.char_indices().last().map_or(0, |(i, _)| i);
+    let (amount, unit) = trimmed.split_at(unit_start);
+    let amount: i64 = amount
+        .parse()
+        .map_err(|_| format!("invalid duration '{}' (expected e.g. 3d, 2w)", input))?;
+
+    let duration = match unit {
+        "d" => Duration::try_days(amount),
+        "w" => Duration::try_weeks(amount),
+        _ => return Err(format!("invalid duration unit in '{}' (use d or w)", input)),
+    };
+
+    duration.ok_or_else(|| format!("invalid duration '{}' (too large)", input))
+}
+
+fn parse_date(input: &str) -> Result<NaiveDate, String> {
+    let today = Local::now().date_naive();
+
+    match input.trim().to_lowercase().as_str() {
+        "today" => return Ok(today),
+        "tomorrow" => return Ok(today + Duration::days(1)),
+        _ => {}
+    }
+
+    if let Ok(date) = NaiveDate::parse_from_str(input.trim(), "%Y-%m-%d") {
+        return Ok(date);
+    }
+
+    parse_duration(input)
+        .ok()
+        .and_then(|duration| today.checked_add_signed(duration))
+        .ok_or_else(|| format!("invalid date '{}' (expected YYYY-MM-DD, today, tomorrow or e.g. +3d)", input))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CompareOp {
+
+    fn holds(&self, ordering: Ordering) -> bool {
+        match self {
+            CompareOp::Eq => ordering == Ordering::Equal,
+            CompareOp::Ne => ordering != Ordering::Equal,
+            CompareOp::Lt => ordering == Ordering::Less,
+            CompareOp::Le => ordering != Ordering::Greater,
+            CompareOp::Gt => ordering == Ordering::Greater,
+            CompareOp::Ge => ordering != Ordering::Less,
+        }
+    }
+}
+
+// A parsed `list --where` expression.
+#[derive(Debug, Clone, PartialEq)]
+enum Filter {
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+    Not(Box<Filter>),
+    Status(CompareOp, TaskStatus),
+    Priority(CompareOp, Priority),
+    Tag(CompareOp, String),
+    Due(CompareOp, NaiveDate),
+    HasDue,
+    Overdue,
+}
+
+impl Filter {
+
+    fn matches(&self, task: &Task, today: NaiveDate) -> bool {
+        match self {
+            Filter::And(a, b) => a.matches(task, today) && b.matches(task, today),
+            Filter::Or(a, b) => a.matches(task, today) || b.matches(task, today),
+            Filter::Not(inner) => !inner.matches(task, today),
+            Filter::Status(op, status) => (task.status == *status) == (*op == CompareOp::Eq),
+            Filter::Priority(op, priority) => match task.priority {
+                Some(p) => op.holds(p.cmp(priority)),
+                None => *op == CompareOp::Ne,
+            },
+            Filter::Tag(op, tag) => task.tags.contains(tag) == (*op == CompareOp::Eq),
+            Filter::Due(op, date) => task.due.is_some_and(|due| op.holds(due.cmp(date))),
+            Filter::HasDue => task.due.is_some(),
+            Filter::Overdue => task.is_overdue(today),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum FilterToken {
+    Word(String),
+    Op(CompareOp),
+    Open,
+    Close,
+}
+
+// Tokens paired with their 1-based column, for error messages.
+fn tokenize_filter(input: &str) -> Result<Vec<(usize, FilterToken)>, String> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let column = i + 1;
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        let (token, len) = match (c, next) {
+            (c, _) if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            ('(', _) => (FilterToken::Open, 1),
+            (')', _) => (FilterToken::Close, 1),
+            ('=', Some('=')) => (FilterToken::Op(CompareOp::Eq), 2),
+            ('=', _) => (FilterToken::Op(CompareOp::Eq), 1),
+            ('!', Some('=')) => (FilterToken::Op(CompareOp::Ne), 2),
+            ('<', Some('=')) => (FilterToken::Op(CompareOp::Le), 2),
+            ('<', _) => (FilterToken::Op(CompareOp::Lt), 1),
+            ('>', Some('=')) => (FilterToken::Op(CompareOp::Ge), 2),
+            ('>', _) => (FilterToken::Op(CompareOp::Gt), 1),
+            (c, _) if c.is_alphanumeric() || "-_+#:.".contains(c) => {
+                let len = chars[i..]
+                    .iter()
+                    .take_while(|c| c.is_alphanumeric() || "-_+#:.".contains(**c))
+                    .count();
+                (FilterToken::Word(chars[i..i + len].iter().collect()), len)
+            }
+            (c, _) => return Err(format!("at column {}: unexpected '{}'", column, c)),
+        };
+
+        tokens.push((column, token));
+        i += len;
+    }
+
+    Ok(tokens)
+}
+
+struct FilterParser {
+    tokens: Vec<(usize, FilterToken)>,
+    pos: usize,
+    end_column: usize,
+}
+
+impl FilterParser {
+
+    fn column(&self) -> usize {
+        self.tokens.get(self.pos).map_or(self.end_column, |(column, _)| *column)
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("at column {}: {}", self.column(), message)
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.tokens.get(self.pos), Some((_, FilterToken::Word(w))) if w.eq_ignore_ascii_case(keyword))
+    }
+
+    fn parse_or(&mut self) -> Result<Filter, String> {
+        let mut left = self.parse_and()?;
+        while self.peek_keyword("or") {
+            self.pos += 1;
+            left = Filter::Or(Box::new(left), Box::new(self.parse_and()?));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Filter, String> {
+        let mut left = self.parse_unary()?;
+        while self.peek_keyword("and") {
+            self.pos += 1;
+            left = Filter::And(Box::new(left), Box::new(self.parse_unary()?));
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<Filter, String> {
+        if self.peek_keyword("not") {
+            self.pos += 1;
+            return Ok(Filter::Not(Box::new(self.parse_unary()?)));
+        }
+
+        if self.tokens.get(self.pos).map(|(_, t)| t) == Some(&FilterToken::Open) {
+            self.pos += 1;
+            let inner = self.parse_or()?;
+            if self.tokens.get(self.pos).map(|(_, t)| t) != Some(&FilterToken::Close) {
+                return Err(self.error("expected ')'"));
+            }
+            self.pos += 1;
+            return Ok(inner);
+        }
+
+        self.parse_condition()
+    }
+
+    fn parse_condition(&mut self) -> Result<Filter, String> {
+        let field = match self.tokens.get(self.pos) {
+            Some((_, FilterToken::Word(word))) if !["and", "or", "not"].contains(&word.to_lowercase().as_str()) => {
+                word.to_lowercase()
+            }
+            _ => return Err(self.error("expected a field (status, priority, tag, overdue or due)")),
+        };
+        let field_column = self.column();
+        self.pos += 1;
+
+        let comparison = match self.tokens.get(self.pos) {
+            Some((_, FilterToken::Op(op))) => {
+                let op = *op;
+                self.pos += 1;
+                match self.tokens.get(self.pos) {
+                    Some((column, FilterToken::Word(value))) => {
+                        let (column, value) = (*column, value.clone());
+                        self.pos += 1;
+                        Some((op, column, value))
+                    }
+                    _ => return Err(self.error("expected a value")),
+                }
+            }
+            _ => None,
+        };
+
+        let at = |column: usize, message: String| format!("at column {}: {}", column, message);
+        let equality_only = |op: CompareOp, column: usize| match op {
+            CompareOp::Eq | CompareOp::Ne => Ok(op),
+            _ => Err(at(column, format!("{} only supports = and !=", field))),
+        };
+
+        match (field.as_str(), comparison) {
+            ("overdue", None) => Ok(Filter::Overdue),
+            ("due", None) => Ok(Filter::HasDue),
+            ("due", Some((op, column, value))) => {
+                parse_date(&value).map(|date| Filter::Due(op, date)).map_err(|e| at(column, e))
+            }
+            ("status", Some((op, column, value))) => {
+                let status = match value.to_lowercase().as_str() {
+                    "todo" | "pending" => TaskStatus::Todo,
+                    "done" => TaskStatus::Done,
+                    _ => return Err(at(column, format!("unknown status '{}' (expected todo or done)", value))),
+                };
+                Ok(Filter::Status(equality_only(op, field_column)?, status))
+            }
+            ("priority", Some((op, column, value))) => Priority::from_str(&value, true)
+                .map(|priority| Filter::Priority(op, priority))
+                .map_err(|_| at(column, format!("unknown priority '{}' (expected low, medium or high)", value))),
+            ("tag", Some((op, column, value))) => {
+                let tag = parse_tag(&value).map_err(|e| at(column, e))?;
+                Ok(Filter::Tag(equality_only(op, field_column)?, tag))
+            }
+            ("status" | "priority" | "tag", None) => Err(self.error(&format!("expected a comparison after '{}'", field))),
+            ("overdue", Some((_, column, _))) => Err(at(column, "overdue doesn't take a value".to_string())),
+            _ => Err(at(field_column, format!("unknown field '{}' (expected status, priority, tag, overdue or due)", field))),
+        }
+    }
+}
+
+fn parse_filter(input: &str) -> Result<Filter, String> {
+    let mut parser = FilterParser {
+        tokens: tokenize_filter(input)?,
+        pos: 0,
+        end_column: input.chars().count() + 1,
+    };
+
+    let filter = parser.parse_or()?;
+    if parser.pos < parser.tokens.len() {
+        return Err(parser.error("unexpected input after the expression"));
+    }
+
+    Ok(filter)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Backend {
+    Snapshot,
+    Log,
+}
+
+impl Backend {
+
+    fn from_env() -> Self {
+        match std::env::var("TODO_BACKEND") {
+            Ok(value) if value == "log" => Backend::Log,
+            _ => Backend::Snapshot,
+        }
+    }
+}
+
+const COMPACT_AFTER_EVENTS: usize = 100;
+
+const DEFAULT_AUTO_PRIORITY_DAYS: i64 = 3;
+
+const VELOCITY_WINDOW_DAYS: i64 = 14;
+
+// What each on-disk format version added, as (top-level fields, task fields); a file
+// of version N holds everything up to N. Adding a persisted field means a new entry here.
+const FORMAT_FIELDS: [(&[&str], &[&str]); 10] = [
+    (&["tasks", "next_id", "version"], &["id", "description", "status"]),
+    (&[], &["due", "prev_descriptions", "completed_at", "priority", "weight", "source", "start"]),
+    (&[], &["created_at"]),
+    (&[], &["flags"]),
+    (&[], &["tags"]),
+    (&[], &["color"]),
+    (&[], &["updated_at"]),
+    (&[], &["auto_priority"]),
+    (&["frozen"], &[]),
+    (&[], &["estimate_mins"]),
+];
+const FORMAT_VERSION: u32 = FORMAT_FIELDS.len() as u32;
+
+// 0 means "write the current format".
+static COMPAT_VERSION: AtomicU32 = AtomicU32::new(0);
+
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+
+// Held for every write to the task files. Ctrl-C takes it before exiting, so it waits
+// for a save in flight to finish and no new one can start in between.
+static SAVE_LOCK: Mutex<()> = Mutex::new(());
+
+fn saving() -> MutexGuard<'static, ()> {
+    SAVE_LOCK.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn on_interrupt() {
+    if !matches!(SAVE_LOCK.try_lock(), Err(TryLockError::WouldBlock)) {
+        std::process::exit(130);
+    }
+
+    let _saved = saving();
+    eprintln!("{} Interrupted, saved progress.", emoji("⚠").yellow().bold());
+    std::process::exit(130);
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum Event {
+    Add { task: Task },
+    // Carries the whole task, so two writers editing the same task concurrently is last-write-wins.
+    Update { task: Task },
+    Remove { id: usize },
+    Reorder { ids: Vec<usize> },
+}
+
+#[derive(Debug, Serialize)]
+struct Issue {
+    code: &'static str,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+struct TodoList {
+    tasks: Vec<Task>,    
+    next_id: usize,      
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    frozen: bool,
+    #[serde(skip)]
+    baseline: Vec<Task>,
+}
+
+impl TodoList {
+    
+    fn new() -> Self {
+        TodoList {
+            tasks: Vec::new(),   
+            next_id: 1,          
+            frozen: false,
+            baseline: Vec::new(),
+        }
+    }
+
+    fn get_file_path() -> String {
+        "tasks.json".to_string()
+    }
+
+    fn get_events_path() -> String {
+        "events.jsonl".to_string()
+    }
+
+    // Where `compact` moves the log while folding it into the snapshot.
+    fn get_rotated_events_path() -> String {
+        format!("{}.1", Self::get_events_path())
+    }
+
+    fn load() -> io::Result<Self> {
+        let mut todo_list = Self::load_snapshot()?;
+
+        if Backend::from_env() == Backend::Log {
+            todo_list.replay_events()?;
+        }
+        todo_list.baseline = todo_list.tasks.clone();
+
+        Ok(todo_list)
+    }
+
+    fn save(&mut self) -> io::Result<()> {
+        Self::ensure_writable()?;
+        self.touch_changed();
+
+        let _saving = saving();
+        match Backend::from_env() {
+            Backend::Snapshot => self.save_snapshot(),
+            Backend::Log => self.append_events(),
+        }
+    }
+
+    // Stamp tasks that differ from what was loaded, so `export --since-file` can find them.
+    fn touch_changed(&mut self) {
+        let now = Local::now();
+
+        for task in &mut self.tasks {
+            if self.baseline.iter().find(|old| old.id == task.id) != Some(task) {
+                task.updated_at = Some(now);
+            }
+        }
+    }
+
+    // `frozen` isn't a task change, so it can't go through the event log; write a fresh snapshot.
+    fn set_frozen(&mut self, frozen: bool) -> io::Result<()> {
+        self.frozen = frozen;
+        let _saving = saving();
+
+        if Backend::from_env() == Backend::Log {
+            // Fold the log in first, so events appended meanwhile stay in it instead of being dropped.
+            Self::fold_log()?;
+            let mut snapshot = Self::load_snapshot()?;
+            snapshot.frozen = frozen;
+            return snapshot.save_snapshot();
+        }
+
+        self.save_snapshot()
+    }
+
+    fn get_backup_dir() -> String {
+        "backups".to_string()
+    }
+
+    fn backup() -> io::Result<std::path::PathBuf> {
+        let _saving = saving();
+        if Backend::from_env() == Backend::Log {
+            Self::fold_log()?;
+        }
+
+        let source = Self::get_file_path();
+        if !std::path::Path::new(&source).exists() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("{} does not exist yet", source)));
+        }
+
+        let dir = Self::get_backup_dir();
+        fs::create_dir_all(&dir)?;
+
+        let name = format!("tasks-{}.json", Local::now().format("%Y%m%d-%H%M%S"));
+        let target = std::path::Path::new(&dir).join(name);
+        fs::copy(source, &target)?;
+
+        Ok(target)
+    }
+
+    fn list_backups() -> io::Result<Vec<String>> {
+        let dir = Self::get_backup_dir();
+
+        if !std::path::Path::new(&dir).exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut names: Vec<String> = fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .filter(|name| name.starts_with("tasks-") && name.ends_with(".json"))
+            .collect();
+        names.sort();
+
+        Ok(names)
+    }
+
+    fn restore(file: &str) -> io::Result<std::path::PathBuf> {
+        let mut path = std::path::PathBuf::from(file);
+        if !path.exists() {
+            path = std::path::Path::new(&Self::get_backup_dir()).join(file);
+        }
+
+        let contents = fs::read_to_string(&path)?;
+        serde_json::from_str::<TodoList>(&contents)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("not a valid task file: {}", e)))?;
+
+        let _saving = saving();
+
+        // The restored snapshot is the whole state; drop events recorded on top of the old one.
+        // Unlinking first means anything appended from here on lands in a fresh log on top of it.
+        for log in [Self::get_rotated_events_path(), Self::get_events_path()] {
+            if std::path::Path::new(&log).exists() {
+                fs::remove_file(log)?;
+            }
+        }
+
+        fs::write(Self::get_file_path(), contents)?;
+
+        Ok(path)
+    }
+
+    fn load_snapshot() -> io::Result<Self> {
+        let path = Self::get_file_path();
+
+        if !std::path::Path::new(&path).exists() {
+            return Ok(Self::new());
+        }
+
+        let contents = fs::read_to_string(path)?;
+        
+        let todo_list: TodoList = serde_json::from_str(&contents)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        Ok(todo_list)
+    }
+
+    fn to_versioned_json(&self, version: u32) -> serde_json::Result<serde_json::Value> {
+        let mut value = serde_json::to_value(self)?;
+        value["version"] = serde_json::json!(version);
+
+        let known = &FORMAT_FIELDS[..version.clamp(1, FORMAT_VERSION) as usize];
+        let list_fields: Vec<&str> = known.iter().flat_map(|(list, _)| list.iter().copied()).collect();
+        let task_fields: Vec<&str> = known.iter().flat_map(|(_, task)| task.iter().copied()).collect();
+
+        if let Some(list) = value.as_object_mut() {
+            list.retain(|key, _| list_fields.contains(&key.as_str()));
+        }
+        if let Some(tasks) = value["tasks"].as_array_mut() {
+            for task in tasks.iter_mut().filter_map(|t| t.as_object_mut()) {
+                task.retain(|key, _| task_fields.contains(&key.as_str()));
+            }
+        }
+
+        Ok(value)
+    }
+
+    fn ensure_writable() -> io::Result<()> {
+        if READ_ONLY.load(AtomicOrdering::Relaxed) {
+            return Err(io::Error::new(io::ErrorKind::PermissionDenied, "read-only mode is on (TODO_READONLY / --read-only)"));
+        }
+        Ok(())
+    }
+
+    fn save_snapshot(&self) -> io::Result<()> {
+        Self::ensure_writable()?;
+        let path = Self::get_file_path();
+
+        let version = match COMPAT_VERSION.load(AtomicOrdering::Relaxed) {
+            0 => FORMAT_VERSION,
+            pinned => pinned,
+        };
+        
+        let json = self.to_versioned_json(version)
+            .and_then(|value| serde_json::to_string_pretty(&value))
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        fs::write(path, json)?;
+        
+        Ok(())  
+    }
+
+    // Replays a log left behind by an interrupted compaction, then the live one.
+    fn replay_events(&mut self) -> io::Result<usize> {
+        let mut replayed = 0;
+
+        for path in [Self::get_rotated_events_path(), Self::get_events_path()] {
+            if std::path::Path::new(&path).exists() {
+                replayed += self.replay_log(&fs::read_to_string(path)?)?;
+            }
+        }
+
+        Ok(replayed)
+    }
+
+    fn replay_log(&mut self, contents: &str) -> io::Result<usize> {
+        let lines: Vec<&str> = contents.lines().filter(|l| !l.trim().is_empty()).collect();
+
+        for (index, line) in lines.iter().enumerate() {
+            match serde_json::from_str::<Event>(line) {
+                Ok(event) => self.apply_event(event),
+                // A writer that died mid-append leaves a torn final line; skip it.
+                Err(_) if index == lines.len() - 1 && !contents.ends_with('\n') => {}
+                Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+            }
+        }
+
+        Ok(lines.len())
+    }
+
+    fn apply_event(&mut self, event: Event) {
+        match event {
+            Event::Add { mut task } => {
+                // Already applied, e.g. a rotated log replayed again after a compaction was cut short.
+                // Later updates may have changed everything else, so match on what they can't.
+                if task.created_at.is_some()
+                    && self.tasks.iter().any(|t| t.id == task.id && t.created_at == task.created_at)
+                {
+                    return;
+                }
+                // Two writers may have handed out the same id concurrently.
+                if self.tasks.iter().any(|t| t.id == task.id) {
+                    task.id = self.next_id;
+                }
+                self.next_id = self.next_id.max(task.id + 1);
+                self.tasks.push(task);
+            }
+            Event::Update { task } => {
+                if let Some(existing) = self.get_task_mut(task.id) {
+                    *existing = task;
+                }
+            }
+            Event::Remove { id } => {
+                self.tasks.retain(|t| t.id != id);
+            }
+            Event::Reorder { ids } => {
+                self.tasks.sort_by_key(|t| ids.iter().position(|&id| id == t.id).unwrap_or(usize::MAX));
+            }
+        }
+    }
+
+    fn diff_events(&self) -> Vec<Event> {
+        let mut events = Vec::new();
+
+        for old in &self.baseline {
+            if !self.tasks.iter().any(|t| t.id == old.id) {
+                events.push(Event::Remove { id: old.id });
+            }
+        }
+
+        for task in &self.tasks {
+            match self.baseline.iter().find(|t| t.id == task.id) {
+                None => events.push(Event::Add { task: task.clone() }),
+                Some(old) if old != task => events.push(Event::Update { task: task.clone() }),
+                Some(_) => {}
+            }
+        }
+
+        let mut replayed = TodoList::new();
+        replayed.tasks = self.baseline.clone();
+        for event in &events {
+            if let Event::Add { task } | Event::Update { task } = event {
+                replayed.apply_event(Event::Update { task: task.clone() });
+                if !replayed.tasks.iter().any(|t| t.id == task.id) {
+                    replayed.tasks.push(task.clone());
+                }
+            } else if let Event::Remove { id } = event {
+                replayed.apply_event(Event::Remove { id: *id });
+            }
+        }
+
+        let order: Vec<usize> = self.tasks.iter().map(|t| t.id).collect();
+        if replayed.tasks.iter().map(|t| t.id).ne(order.iter().copied()) {
+            events.push(Event::Reorder { ids: order });
+        }
+
+        events
+    }
+
+    fn append_events(&mut self) -> io::Result<()> {
+        let events = self.diff_events();
+
+        if events.is_empty() {
+            return Ok(());
+        }
+
+        let mut buffer = String::new();
+        for event in &events {
+            let line = serde_json::to_string(event)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            buffer.push_str(&line);
+            buffer.push('\n');
+        }
+
+        // One append-mode write per command, so concurrent writers interleave
+        // whole batches instead of clobbering each other's snapshot.
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(Self::get_events_path())?;
+        Self::drop_torn_tail(&mut file)?;
+        file.write_all(buffer.as_bytes())?;
+
+        self.baseline = self.tasks.clone();
+
+        let logged = fs::read_to_string(Self::get_events_path())?.lines().count();
+        if logged >= COMPACT_AFTER_EVENTS {
+            Self::fold_log()?;
+        }
+
+        Ok(())
+    }
+
+    // A writer that died mid-append leaves a line without its newline. Replay skips it,
+    // but appending onto it would glue the next event to it, so cut it off first.
+    fn drop_torn_tail(file: &mut fs::File) -> io::Result<()> {
+        let mut contents = Vec::new();
+        file.seek(SeekFrom::Start(0))?;
+        file.read_to_end(&mut contents)?;
+
+        if !contents.is_empty() && !contents.ends_with(b"\n") {
+            let keep = contents.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+            file.set_len(keep as u64)?;
+        }
+
+        Ok(())
+    }
+
+    fn compact() -> io::Result<usize> {
+        let _saving = saving();
+        Self::fold_log()
+    }
+
+    // Moves the log aside before folding it in, so events appended meanwhile go to a
+    // fresh log instead of being wiped along with the ones already in the snapshot.
+    fn fold_log() -> io::Result<usize> {
+        let live = Self::get_events_path();
+        let rotated = Self::get_rotated_events_path();
+        let mut todo_list = Self::load_snapshot()?;
+
+        // A leftover rotated log from an interrupted compaction is finished first.
+        if !std::path::Path::new(&rotated).exists() {
+            if !std::path::Path::new(&live).exists() {
+                todo_list.save_snapshot()?;
+                return Ok(0);
+            }
+            fs::rename(&live, &rotated)?;
+        }
+
+        let contents = fs::read_to_string(&rotated)?;
+        let complete = contents.rfind('\n').map_or(0, |i| i + 1);
+        let replayed = todo_list.replay_log(&contents[..complete])?;
+        todo_list.save_snapshot()?;
+
+        // A writer that opened the log just before the rename may still have appended to it.
+        let late = fs::read(&rotated)?.split_off(complete);
+        if !late.is_empty() {
+            fs::OpenOptions::new().create(true).append(true).open(&live)?.write_all(&late)?;
+        }
+        fs::remove_file(&rotated)?;
+
+        Ok(replayed)
+    }
+
+    fn validate(&self) -> Vec<Issue> {
+        let mut issues = Vec::new();
+
+        let mut seen = BTreeSet::new();
+        for task in &self.tasks {
+            if !seen.insert(task.id) {
+                issues.push(Issue {
+                    code: "duplicate_id",
+                    message: format!("Task #{} appears more than once", task.id),
+                    id: Some(task.id),
+                });
+            }
+
+            if task.description.trim().is_empty() {
+                issues.push(Issue {
+                    code: "empty_description",
+                    message: format!("Task #{} has an empty description", task.id),
+                    id: Some(task.id),
+                });
+            }
+
+            if let Some(color) = task.color.as_deref().filter(|c| parse_color(c).is_err()) {
+                issues.push(Issue {
+                    code: "unknown_color",
+                    message: format!("Task #{} has an unknown color '{}'", task.id, color),
+                    id: Some(task.id),
+                });
+            }
+        }
+
+        if let Some(max_id) = self.tasks.iter().map(|t| t.id).max() {
+            if self.next_id <= max_id {
+                issues.push(Issue {
+                    code: "bad_next_id",
+                    message: format!("next_id is {} but task ids go up to {}", self.next_id, max_id),
+                    id: None,
+                });
+            }
+        }
+
+        issues
+    }
+
+    fn add_task(&mut self, description: String, source: &str) -> usize {
+        let id = self.next_id;                           
+        let mut task = Task::new(id, description);          
+        task.source = Some(source.to_string());
+        self.tasks.push(task);                           
+        self.next_id += 1;                              
+        id                                              
+    }
+
+    fn import_tasks(&mut self, tasks: Vec<Task>, strategy: MergeStrategy) -> ImportReport {
+        let mut report = ImportReport::default();
+
+        for mut task in tasks {
+            task.source = Some("import".to_string());
+
+            // Same rule as `dedupe`: descriptions match ignoring case and surrounding space.
+            let key = task.description.trim().to_lowercase();
+            let existing = self.tasks.iter().position(|t| t.description.trim().to_lowercase() == key);
+
+            match (strategy, existing) {
+                (MergeStrategy::SkipDuplicates, Some(_)) => report.skipped += 1,
+                (MergeStrategy::Replace, Some(index)) => {
+                    // Update in place: whatever the imported item leaves out stays as it was.
+                    let old = &self.tasks[index];
+                    task.id = old.id;
+                    task.created_at = old.created_at.or(task.created_at);
+                    task.priority = task.priority.or(old.priority);
+                    task.due = task.due.or(old.due);
+                    task.start = task.start.or(old.start);
+                    task.estimate_mins = task.estimate_mins.or(old.estimate_mins);
+                    task.color = task.color.take().or_else(|| old.color.clone());
+                    if task.tags.is_empty() {
+                        task.tags = old.tags.clone();
+                    }
+                    if task.flags.is_empty() {
+                        task.flags = old.flags.clone();
+                    }
+                    if task.prev_descriptions.is_empty() {
+                        task.prev_descriptions = old.prev_descriptions.clone();
+                    }
+                    if is_default_weight(&task.weight) {
+                        task.weight = old.weight;
+                    }
+                    if task.priority == old.priority {
+                        task.auto_priority = old.auto_priority;
+                    }
+                    report.replaced.push(task.id);
+                    self.tasks[index] = task;
+                }
+                _ => {
+                    task.id = self.next_id;
+                    self.next_id += 1;
+                    report.added.push(task.id);
+                    self.tasks.push(task);
+                }
+            }
+        }
+
+        report
+    }
+
+    fn get_task_mut(&mut self, id: usize) -> Option<&mut Task> {
+        self.tasks.iter_mut().find(|t| t.id == id)
+    }
+
+    fn mark_done(&mut self, id: usize) -> bool {
+        
+        if let Some(task) = self.tasks.iter_mut().find(|t| t.id == id) {
+            task.mark_done();
+            true
+        } else {
+            false  
+        }
+    }
+
+    fn mark_todo(&mut self, id: usize) -> bool {
+        if let Some(task) = self.tasks.iter_mut().find(|t| t.id == id) {
+            task.mark_todo();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn set_weight(&mut self, id: usize, weight: f32) -> bool {
+        if let Some(task) = self.get_task_mut(id) {
+            task.weight = weight;
+            true
+        } else {
+            false
+        }
+    }
+
+    // Ids of pending, due-soon tasks raised to high priority.
+    fn auto_prioritize(&mut self, today: NaiveDate, within: Duration) -> Vec<usize> {
+        let mut raised = Vec::new();
+        let limit = today.checked_add_signed(within).unwrap_or(NaiveDate::MAX);
+
+        for task in self.tasks.iter_mut().filter(|t| t.status == TaskStatus::Todo) {
+            let due_soon = task.due.is_some_and(|due| due <= limit);
+            let manual = task.priority.is_some() && !task.auto_priority;
+
+            if due_soon && !manual && task.priority != Some(Priority::High) {
+                task.priority = Some(Priority::High);
+                task.auto_priority = true;
+                raised.push(task.id);
+            }
+        }
+
+        raised
+    }
+
+    fn reset_tagged(&mut self, tag: &str) -> usize {
+        let mut reset = 0;
+
+        for task in self.tasks.iter_mut().filter(|t| t.status == TaskStatus::Done && t.tags.contains(tag)) {
+            task.mark_todo();
+            reset += 1;
+        }
+
+        reset
+    }
+
+    // None, with nothing changed, if any due date would be pushed out of range.
+    fn reschedule_all(&mut self, by: Duration) -> Option<usize> {
+        let pending = |t: &&mut Task| t.status == TaskStatus::Todo && t.due.is_some();
+
+        let shifted: Vec<NaiveDate> = self.tasks
+            .iter_mut()
+            .filter(pending)
+            .map(|t| t.due.and_then(|due| due.checked_add_signed(by)))
+            .collect::<Option<_>>()?;
+
+        for (task, due) in self.tasks.iter_mut().filter(pending).zip(&shifted) {
+            task.due = Some(*due);
+        }
+
+        Some(shifted.len())
+    }
+
+    fn move_task(&mut self, id: usize, position: usize) -> bool {
+        let Some(from) = self.tasks.iter().position(|t| t.id == id) else {
+            return false;
+        };
+
+        let task = self.tasks.remove(from);
+        let to = position.saturating_sub(1).min(self.tasks.len());
+        self.tasks.insert(to, task);
+        true
+    }
+
+    // None if there's no such task, otherwise whether the flag changed.
+    fn set_flag(&mut self, id: usize, flag: &str, on: bool) -> Option<bool> {
+        let task = self.get_task_mut(id)?;
+
+        if on {
+            Some(task.flags.insert(flag.to_string()))
+        } else {
+            Some(task.flags.remove(flag))
+        }
+    }
+
+    fn edit_task(&mut self, id: usize, description: String) -> bool {
+        if let Some(task) = self.get_task_mut(id) {
+            task.description = description;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn rename_task(&mut self, id: usize, description: String) -> bool {
+        if let Some(task) = self.get_task_mut(id) {
+            task.rename(description);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn remove_task(&mut self, id: usize) -> bool {
+
+        if let Some(pos) = self.tasks.iter().position(|t| t.id == id) {
+            self.tasks.remove(pos);  
+            true
+        } else {
+            false
+        }
+    }
+
+    fn select(&self, view: ListView) -> Vec<&Task> {
+        match view {
+            ListView::All => self.tasks.iter().collect(),
+            ListView::Todo => self.tasks
+                .iter()
+                .filter(|t| t.status == TaskStatus::Todo)
+                .collect(),
+            ListView::Done => self.tasks
+                .iter()
+                .filter(|t| t.status == TaskStatus::Done)
+                .collect(),
+            ListView::DueWithin(within) => self.due_within(Local::now().date_naive(), within),
+        }
+    }
+
+    fn filtered(&self, view: ListView, options: &ListOptions) -> Vec<&Task> {
+        let mut tasks = self.select(view);
+        tasks.retain(|t| options.matches(t));
+        sort_tasks(&mut tasks, &options.sort);
+
+        if view == ListView::All {
+            match options.done_position {
+                DonePosition::Interleaved => {}
+                DonePosition::Bottom => tasks.sort_by_key(|t| t.status == TaskStatus::Done),
+                DonePosition::Hidden => tasks.retain(|t| t.status == TaskStatus::Todo),
+            }
+        }
+        tasks
+    }
+
+    fn list(&self, view: ListView, options: &ListOptions, config: &Config) {
+        let tasks = self.filtered(view, options);
+
+        if tasks.is_empty() {
+            if self.select(view).is_empty() {
+                println!("{}", view.empty_message());
+            } else {
+                println!("{}", "No tasks match those filters.".yellow());
+            }
+            return;
+        }
+
+        let gap = if config.compact_spacing { "" } else { "\n" };
+
+        println!("{}{}{}", gap, view.title(), gap);
+
+        let cells: Vec<String> = match options.columns {
+            Some(_) => tasks.iter().map(|task| task.render(config, None)).collect(),
+            None => Vec::new(),
+        };
+        let grid = options.columns.and_then(|columns| layout_columns(&cells, columns, terminal_width()));
+
+        for task in tasks.iter().filter(|_| grid.is_none()) {
+            match options.max_width {
+                // A hard cap replaces wrapping: one line per task, cut to fit.
+                Some(max_width) => println!("{}", truncate_visible(&task.render(config, None), max_width)),
+                None => task.display(config),
+            }
+        }
+        for line in grid.iter().flatten() {
+            println!("{}", line);
+        }
+
+        if options.summary {
+            let line = summary_line(&tasks, Local::now().date_naive(), options.pending_total);
+            println!("{}{}", gap, line.bright_black());
+        }
+        print!("{}", gap);
+    }
+
+    // Groups in display order; `None` collects tasks without a priority or tags.
+    fn count_by(&self, view: ListView, key: CountKey) -> Vec<(Option<String>, usize)> {
+        let tasks = self.select(view);
+
+        match key {
+            CountKey::Tag => {
+                let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+                for task in &tasks {
+                    for tag in &task.tags {
+                        *counts.entry(tag.clone()).or_default() += 1;
+                    }
+                }
+                let untagged = tasks.iter().filter(|t| t.tags.is_empty()).count();
+
+                let mut groups: Vec<(Option<String>, usize)> = counts.into_iter().map(|(tag, count)| (Some(tag), count)).collect();
+                if untagged > 0 {
+                    groups.push((None, untagged));
+                }
+                groups
+            }
+            CountKey::Priority => {
+                let mut counts: BTreeMap<Option<Priority>, usize> = BTreeMap::new();
+                for task in &tasks {
+                    *counts.entry(task.priority).or_default() += 1;
+                }
+
+                counts
+                    .into_iter()
+                    .rev()
+                    .map(|(priority, count)| (priority.map(|p| format!("{:?}", p).to_lowercase()), count))
+                    .collect()
+            }
+            CountKey::Status => {
+                let done = tasks.iter().filter(|t| t.status == TaskStatus::Done).count();
+                [(Some("todo".to_string()), tasks.len() - done), (Some("done".to_string()), done)]
+                    .into_iter()
+                    .filter(|(_, count)| *count > 0)
+                    .collect()
+            }
+        }
+    }
+
+    fn due_within(&self, today: NaiveDate, within: Duration) -> Vec<&Task> {
+        // A window reaching past the last representable date just means "everything from today".
+        let limit = today.checked_add_signed(within).unwrap_or(NaiveDate::MAX);
+
+        self.tasks
+            .iter()
+            .filter(|t| t.status == TaskStatus::Todo)
+            .filter(|t| t.due.is_some_and(|due| due >= today && due <= limit))
+            .collect()
+    }
+
+    fn duplicate_groups(&self) -> Vec<Vec<usize>> {
+        let mut groups: Vec<(String, Vec<usize>)> = Vec::new();
+
+        for task in &self.tasks {
+            let key = task.description.trim().to_lowercase();
+            match groups.iter_mut().find(|(k, _)| *k == key) {
+                Some((_, ids)) => ids.push(task.id),
+                None => groups.push((key, vec![task.id])),
+            }
+        }
+
+        groups
+            .into_iter()
+            .map(|(_, mut ids)| {
+                ids.sort();
+                ids
+            })
+            .filter(|ids| ids.len() > 1)
+            .collect()
+    }
+
+    fn merge_duplicates(&mut self) -> usize {
+        let mut removed = 0;
+
+        for ids in self.duplicate_groups() {
+            let (keep, rest) = ids.split_first().expect("duplicate groups have at least two ids");
+
+            // Done wins: if any copy was completed, the kept task is too.
+            let done_copies: Vec<&Task> = self.tasks
+                .iter()
+                .filter(|t| ids.contains(&t.id) && t.status == TaskStatus::Done)
+                .collect();
+            let any_done = !done_copies.is_empty();
+            let completed = done_copies.iter().filter_map(|t| t.completed_at).min();
+            let copies = || self.tasks.iter().filter(|t| ids.contains(&t.id));
+            let flags: BTreeSet<String> = copies().flat_map(|t| t.flags.iter().cloned()).collect();
+            let tags: BTreeSet<String> = copies().flat_map(|t| t.tags.iter().cloned()).collect();
+
+            if let Some(task) = self.get_task_mut(*keep) {
+                task.flags = flags;
+                task.tags = tags;
+                if any_done {
+                    task.mark_done();
+                    task.completed_at = completed.or(task.completed_at);
+                }
+            }
+
+            for id in rest {
+                if self.remove_task(*id) {
+                    removed += 1;
+                }
+            }
+        }
+
+        removed
+    }
+
+    fn completion_percentage(&self, weighted: bool) -> f64 {
+        let measure = |task: &Task| if weighted { f64::from(task.weight) } else { 1.0 };
+
+        let total: f64 = self.tasks.iter().map(measure).sum();
+        let done: f64 = self.tasks
+            .iter()
+            .filter(|t| t.status == TaskStatus::Done)
+            .map(measure)
+            .sum();
+
+        if total == 0.0 {
+            0.0
+        } else {
+            done / total * 100.0
+        }
+    }
+
+    fn show_eta(&self) {
+        let now = Local::now();
+        let pending: Vec<&Task> = self.select(ListView::Todo);
+
+        println!("\n{}\n", "ETA:".bold().bright_blue());
+
+        if pending.is_empty() {
+            println!("  {}\n", "Nothing pending, the list is already empty.".bright_green());
+            return;
+        }
+
+        let estimates: Vec<u64> = pending.iter().filter_map(|t| t.estimate_mins).map(u64::from).collect();
+        let estimated = if estimates.is_empty() {
+            "no estimates".to_string()
+        } else {
+            format!("{} estimated, {}", estimates.len(), format_minutes(estimates.iter().sum()))
+        };
+        println!("  {:<12} {} task(s) ({})", "Pending:".bold(), pending.len().to_string().bright_red(), estimated);
+
+        let since = now - Duration::days(VELOCITY_WINDOW_DAYS);
+        let completed: Vec<&Task> = self.tasks
+            .iter()
+            .filter(|t| t.status == TaskStatus::Done && t.completed_at.is_some_and(|at| at >= since))
+            .collect();
+        let recent = completed.len();
+
+        if recent == 0 {
+            println!("  {:<12} {}", 
+                     "Velocity:".bold(), 
+                     format!("nothing completed in the last {} days, so there's no pace to project from", 
+                             VELOCITY_WINDOW_DAYS).yellow());
+            println!();
+            return;
+        }
+
+        let done_estimates: Vec<u64> = completed.iter().filter_map(|t| t.estimate_mins).map(u64::from).collect();
+
+        // With estimates on both sides, project minutes of work per day; unestimated pending
+        // tasks count as the average pending estimate. Otherwise fall back to tasks per day.
+        let days = if estimates.is_empty() || done_estimates.is_empty() {
+            let velocity = recent as f64 / VELOCITY_WINDOW_DAYS as f64;
+            println!("  {:<12} {:.1} task(s)/day ({} done in the last {} days)", 
+                     "Velocity:".bold(), 
+                     velocity, 
+                     recent, 
+                     VELOCITY_WINDOW_DAYS);
+            pending.len() as f64 / velocity
+        } else {
+            let pace = done_estimates.iter().sum::<u64>() as f64 / VELOCITY_WINDOW_DAYS as f64;
+            let average = estimates.iter().sum::<u64>() as f64 / estimates.len() as f64;
+            let remaining = estimates.iter().sum::<u64>() as f64 + (pending.len() - estimates.len()) as f64 * average;
+            println!("  {:<12} {}/day ({} estimated task(s) done in the last {} days)", 
+                     "Velocity:".bold(), 
+                     format_minutes(pace.round() as u64), 
+                     done_estimates.len(), 
+                     VELOCITY_WINDOW_DAYS);
+            remaining / pace
+        };
+
+        let days = days.ceil() as i64;
+        match Duration::try_days(days).and_then(|d| now.date_naive().checked_add_signed(d)) {
+            Some(date) => println!("  {:<12} {} (in {} day(s))", 
+                                   "Empty by:".bold(), 
+                                   date.to_string().bright_white().bold(), 
+                                   days),
+            None => println!("  {:<12} {}", "Empty by:".bold(), "not in the foreseeable future".yellow()),
+        }
+        println!();
+    }
+
+    fn show_stats(&self, weighted: bool) {
+        let total = self.tasks.len();
+        let done = self.tasks.iter().filter(|t| t.status == TaskStatus::Done).count();
+        let overdue = self.tasks.iter().filter(|t| t.is_overdue(Local::now().date_naive())).count();
+
+        let percentage = format!("{:.1}%", self.completion_percentage(weighted));
+        let label = if weighted { "Completion (weighted):" } else { "Completion:" };
+
+        println!("\n{}\n", "Statistics:".bold().bright_blue());
+        println!("  {:<23} {}", "Total:".bold(), total.to_string().bright_cyan());
+        println!("  {:<23} {}", "Completed:".bold(), done.to_string().bright_green());
+        println!("  {:<23} {}", "Pending:".bold(), (total - done).to_string().bright_red());
+        println!("  {:<23} {}", "Overdue:".bold(), overdue.to_string().red());
+        println!("  {:<23} {}", label.bold(), percentage.bright_white().bold());
+        println!();
+    }
+
+    fn search(&self, query: &str, use_regex: bool, ignore_case: bool) -> Result<Vec<&Task>, regex::Error> {
+        if use_regex {
+            let re = RegexBuilder::new(query)
+                .case_insensitive(ignore_case)
+                .build()?;
+
+            return Ok(self.tasks
+                .iter()
+                .filter(|t| re.is_match(&t.description))
+                .collect());
+        }
+
+        let matches = if ignore_case {
+            let needle = query.to_lowercase();
+            self.tasks
+                .iter()
+                .filter(|t| t.description.to_lowercase().contains(&needle))
+                .collect()
+        } else {
+            self.tasks
+                .iter()
+                .filter(|t| t.description.contains(query))
+                .collect()
+        };
+
+        Ok(matches)
+    }
+
+    fn clear_done(&mut self, older_than: Option<Duration>, newer_than: Option<Duration>) -> usize {
+        let initial_count = self.tasks.len();
+        let now = Local::now();
+
+        // Tasks completed before completion times were recorded count as old. An age
+        // reaching back past the earliest representable time means nothing else is.
+        let is_older = |task: &Task, age: Duration| {
+            task.completed_at.is_none_or(|completed_at| {
+                now.checked_sub_signed(age).is_some_and(|cutoff| completed_at < cutoff)
+            })
+        };
+       
+        self.tasks.retain(|t| {
+            if t.status == TaskStatus::Todo {
+                return true;
+            }
+
+            let clear = match (older_than, newer_than) {
+                (Some(age), _) => is_older(t, age),
+                (None, Some(age)) => !is_older(t, age),
+                (None, None) => true,
+            };
+            !clear
+        });
+        
+        initial_count - self.tasks.len()  
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+enum IdStyle {
+    #[default]
+    Brackets,
+    Hash,
+    Bare,
+}
+
+impl IdStyle {
+
+    fn plain(&self, id: usize) -> String {
+        match self {
+            IdStyle::Brackets => format!("[{}]", id),
+            IdStyle::Hash => format!("#{}", id),
+            IdStyle::Bare => id.to_string(),
+        }
+    }
+
+    fn styled(&self, id: usize) -> String {
+        match self {
+            IdStyle::Brackets => format!("[{}]", id.to_string().bright_cyan()),
+            IdStyle::Hash | IdStyle::Bare => self.plain(id).bright_cyan().to_string(),
+        }
+    }
+
+    // Messages read "Task #3" unless ids are shown bare.
+    fn in_message(&self, id: usize) -> String {
+        match self {
+            IdStyle::Brackets | IdStyle::Hash => format!("#{}", id),
+            IdStyle::Bare => id.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(default)]
+struct Config {
+    completion_log: Option<String>,
+    wrap_width: usize,
+    wrap_indent: Option<usize>,
+    success_prefix: Option<String>,
+    error_prefix: Option<String>,
+    due_sort_undated_first: bool,
+    compat_version: Option<u32>,
+    pending_total: bool,
+    compact_spacing: bool,
+    tag_colors: BTreeMap<String, String>,
+    auto_priority_days: Option<i64>,
+    id_style: IdStyle,
+    confirm_destructive: bool,
+    done_position: DonePosition,
+    confirm_done: bool,
+}
+
+impl Config {
+
+    fn success_prefix(&self) -> colored::ColoredString {
+        self.success_prefix.as_deref().unwrap_or(emoji("✓")).green().bold()
+    }
+
+    fn error_prefix(&self) -> colored::ColoredString {
+        self.error_prefix.as_deref().unwrap_or(emoji("✗")).red().bold()
+    }
+
+    fn wrap_width(&self) -> Option<usize> {
+        match self.wrap_width {
+            0 => terminal_width(),
+            width => Some(width),
+        }
+    }
+
+    fn get_file_path() -> String {
+        "todo-config.json".to_string()
+    }
+
+    fn load() -> io::Result<Self> {
+        let path = Self::get_file_path();
+
+        if !std::path::Path::new(&path).exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = fs::read_to_string(path)?;
+
+        let config: Config = serde_json::from_str(&contents)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        Ok(config)
+    }
+
+    // Rewrites only `tag_colors`, so the rest of the user's file stays as written.
+    fn save_tag_colors(tag_colors: &BTreeMap<String, String>) -> io::Result<()> {
+        let path = Self::get_file_path();
+
+        let mut value = if std::path::Path::new(&path).exists() {
+            serde_json::from_str(&fs::read_to_string(&path)?)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+        } else {
+            serde_json::json!({})
+        };
+
+        let Some(object) = value.as_object_mut() else {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} isn't a JSON object", path)));
+        };
+        if tag_colors.is_empty() {
+            object.remove("tag_colors");
+        } else {
+            object.insert("tag_colors".to_string(), serde_json::json!(tag_colors));
+        }
+
+        let json = serde_json::to_string_pretty(&value)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(path, json + "\n")
+    }
+}
+
+fn append_completion_log(path: &str, task: &Task) -> io::Result<()> {
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+
+    writeln!(file, "{} #{} {}",
+             Local::now().format("%Y-%m-%d %H:%M:%S"),
+             task.id,
+             task.description)
+}
+
+#[derive(Parser)]
+#[command(name = "todo")]
+#[command(about = "Manage your tasks from the command line", long_about = None)]
+#[command(version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+
+    #[arg(long, global = true)]
+    no_emoji: bool,
+
+    #[arg(long, global = true)]
+    no_color: bool,
+
+    #[arg(long, global = true)]
+    read_only: bool,
+
+    #[arg(long, global = true, value_parser = clap::value_parser!(u32).range(1..=FORMAT_VERSION as i64))]
+    compat_version: Option<u32>,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    
+    Add {
+       
+        description: String,
+
+        #[arg(long, value_parser = parse_date)]
+        due: Option<NaiveDate>,
+
+        #[arg(short, long, value_enum)]
+        priority: Option<Priority>,
+
+        #[arg(long, value_parser = parse_weight, default_value = "1.0")]
+        weight: f32,
+
+        #[arg(long, value_parser = parse_date)]
+        start: Option<NaiveDate>,
+
+        #[arg(long)]
+        estimate: Option<u32>,
+
+        #[arg(long)]
+        at: Option<usize>,
+
+        #[arg(long = "tag", value_parser = parse_tag)]
+        tags: Vec<String>,
+    },
+
+    List {
+       
+        #[arg(short, long)]
+        todo: bool,
+
+        #[arg(short, long)]
+        done: bool,
+
+        #[arg(long, value_delimiter = ',', allow_hyphen_values = true, value_parser = parse_sort_spec)]
+        sort: Vec<SortSpec>,
+
+        #[arg(long)]
+        due_sort_undated_first: bool,
+
+        #[arg(long, value_parser = parse_duration, conflicts_with_all = ["todo", "done"])]
+        due_within: Option<Duration>,
+
+        #[arg(long)]
+        json: bool,
+
+        #[arg(long, requires = "json")]
+        extended: bool,
+
+        #[arg(long)]
+        max_width: Option<usize>,
+
+        #[arg(long, value_parser = parse_columns, conflicts_with = "max_width")]
+        columns: Option<Columns>,
+
+        #[arg(long = "where", value_parser = parse_filter)]
+        filter: Option<Filter>,
+
+        #[arg(long, value_enum, conflicts_with_all = ["todo", "done"])]
+        done_position: Option<DonePosition>,
+
+        #[arg(long)]
+        source: Option<String>,
+
+        #[arg(long)]
+        include_deferred: bool,
+
+        #[arg(long)]
+        summary: bool,
+
+        #[arg(long)]
+        pending_total: bool,
+
+        #[arg(long, value_parser = parse_flag)]
+        flag: Option<String>,
+    },
+
+    Validate {
+
+        #[arg(long)]
+        json: bool,
+    },
+
+    Count {
+
+        #[arg(short, long)]
+        todo: bool,
+
+        #[arg(short, long, conflicts_with = "todo")]
+        done: bool,
+
+        #[arg(long)]
+        pending_total: bool,
+
+        #[arg(long, value_enum)]
+        by: Option<CountKey>,
+
+        #[arg(long, requires = "by")]
+        json: bool,
+    },
+
+    Done {
+      
+        id: usize,
+
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    Undone {
+
+        id: usize,
+    },
+
+    Remove {
+
+        id: usize,
+
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    Mark {
+
+        id: usize,
+
+        #[arg(value_parser = parse_flag)]
+        flag: String,
+    },
+
+    Unmark {
+
+        id: usize,
+
+        #[arg(value_parser = parse_flag)]
+        flag: String,
+    },
+
+    Estimate {
+
+        id: usize,
+
+        minutes: Option<u32>,
+    },
+
+    Eta,
+
+    Weight {
+
+        id: usize,
+
+        #[arg(value_parser = parse_weight)]
+        weight: f32,
+    },
+
+    Stats {
+
+        #[arg(long)]
+        weighted: bool,
+    },
+
+    Color {
+
+        id: usize,
+
+        #[arg(value_parser = parse_color)]
+        color: Option<String>,
+    },
+
+    AutoPrioritize {
+
+        #[arg(long, value_parser = parse_duration)]
+        within: Option<Duration>,
+    },
+
+    Tag {
+
+        #[command(subcommand)]
+        action: TagAction,
+    },
+
+    Reset {
+
+        #[arg(long, value_parser = parse_tag)]
+        tag: String,
+
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    RescheduleAll {
+
+        #[arg(value_parser = parse_duration)]
+        by: Duration,
+
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    Move {
+
+        id: usize,
+
+        position: usize,
+    },
+
+    Top {
+
+        id: usize,
+    },
+
+    Bottom {
+
+        id: usize,
+    },
+
+    Edit {
+
+        id: Option<usize>,
+
+        description: Option<String>,
+
+        #[arg(short, long)]
+        interactive: bool,
+    },
+
+    Rename {
+
+        id: usize,
+
+        description: String,
+    },
+
+    Show {
+
+        id: usize,
+    },
+
+    #[cfg(feature = "clipboard")]
+    Copy {
+
+        id: usize,
+
+        #[arg(long, value_enum, default_value = "description")]
+        format: CopyFormat,
+    },
+
+    Clear {
+
+        #[arg(long, value_parser = parse_duration, conflicts_with = "newer_than")]
+        older_than: Option<Duration>,
+
+        #[arg(long, value_parser = parse_duration)]
+        newer_than: Option<Duration>,
+
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    Compact,
+
+    Freeze,
+
+    Unfreeze,
+
+    Cat,
+
+    #[cfg(feature = "schema")]
+    #[command(hide = true)]
+    Schema,
+
+    Watch {
+
+        #[arg(short, long, default_value_t = 5, value_parser = clap::value_parser!(u64).range(1..))]
+        interval: u64,
+
+        #[arg(long)]
+        bell: bool,
+    },
+
+    Import {
+
+        file: Option<String>,
+
+        #[arg(long, value_enum)]
+        input_format: Option<InputFormat>,
+
+        #[arg(long, value_enum, default_value = "new-ids")]
+        merge_strategy: MergeStrategy,
+    },
+
+    Dedupe {
+
+        #[arg(long)]
+        merge: bool,
+
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    Backup {
+
+        #[command(subcommand)]
+        action: Option<BackupAction>,
+    },
+
+    Export {
+
+        #[arg(long, value_enum, default_value = "tsv")]
+        format: ExportFormat,
+
+        #[arg(short, long)]
+        out: Option<String>,
+
+        #[arg(long)]
+        since_file: Option<String>,
+
+        #[arg(long, requires = "since_file")]
+        reset: bool,
+
+        #[arg(long, requires = "out")]
+        append: bool,
+    },
+
+    Search {
+
+        query: String,
+
+        #[arg(long)]
+        regex: bool,
+
+        #[arg(short, long)]
+        ignore_case: bool,
+
+        #[arg(long)]
+        json: bool,
+
+        #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+        limit: Option<u64>,
+
+        #[arg(long, requires = "limit", default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+        page: u64,
+    },
+}
+
+impl Commands {
+
+    // Commands that leave tasks.json, the event log and the config alone; the only ones
+    // allowed with --read-only. `export` may still write the files it's pointed at.
+    fn is_read_only(&self) -> bool {
+        match self {
+            Commands::List { .. }
+            | Commands::Count { .. }
+            | Commands::Validate { .. }
+            | Commands::Stats { .. }
+            | Commands::Eta
+            | Commands::Show { .. }
+            | Commands::Search { .. }
+            | Commands::Export { .. }
+            | Commands::Watch { .. }
+            | Commands::Cat
+            | Commands::Tag { action: TagAction::Color { list: true, .. } }
+            | Commands::Backup { action: Some(BackupAction::List) } => true,
+            #[cfg(feature = "clipboard")]
+            Commands::Copy { .. } => true,
+            #[cfg(feature = "schema")]
+            Commands::Schema => true,
+            Commands::Dedupe { merge, .. } => !merge,
+            _ => false,
+        }
+    }
+
+    // A frozen list also allows commands that don't change its tasks.
+    fn allowed_when_frozen(&self) -> bool {
+        self.is_read_only()
+            || matches!(self, Commands::Compact
+                | Commands::Freeze
+                | Commands::Unfreeze
+                | Commands::Tag { .. }
+                | Commands::Backup { action: None })
+    }
+}
+
+#[derive(Subcommand)]
+enum TagAction {
+
+    Color {
+
+        #[arg(value_parser = parse_tag, required_unless_present = "list")]
+        tag: Option<String>,
+
+        #[arg(value_parser = parse_color, required_unless_present_any = ["clear", "list"])]
+        color: Option<String>,
+
+        #[arg(long, conflicts_with = "color")]
+        clear: bool,
+
+        #[arg(long, conflicts_with_all = ["tag", "clear"])]
+        list: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum BackupAction {
+
+    List,
+
+    Restore {
+
+        file: String,
+
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+
+    let cli = Cli::parse();
+
+    let env_flag = |name: &str| std::env::var(name).is_ok_and(|v| !v.is_empty() && v != "0");
+    NO_EMOJI.store(cli.no_emoji || env_flag("TODO_NO_EMOJI"), AtomicOrdering::Relaxed);
+    READ_ONLY.store(cli.read_only || env_flag("TODO_READONLY"), AtomicOrdering::Relaxed);
+
+    if cli.no_color {
+        colored::control::set_override(false);
+    }
+
+    // Without a handler Ctrl-C keeps its default behavior, so a failure here is fine.
+    let _ = ctrlc::set_handler(on_interrupt);
+    
+// Falling back to defaults would quietly drop every setting, safety prompts included.
+let config = match Config::load() {
+    Ok(config) => config,
+    Err(e) => {
+        eprintln!("{} Can't load {}: {}", 
+                 Config::default().error_prefix(), 
+                 Config::get_file_path(), 
+                 e);
+        std::process::exit(1);
+    }
+};
+
+if let Some(version) = cli.compat_version.or(config.compat_version) {
+    COMPAT_VERSION.store(version.clamp(1, FORMAT_VERSION), AtomicOrdering::Relaxed);
+}
+
+let mut todo_list = match TodoList::load() {
+    Ok(list) => list,
+    // Starting from an empty list here would let the next save log events on top of nothing.
+    Err(e) if Backend::from_env() == Backend::Log && !matches!(cli.command, Commands::Validate { .. }) => {
+        eprintln!("{} Can't load the task list: {}", config.error_prefix(), e);
+        std::process::exit(1);
+    }
+    Err(_) => TodoList::new(),
+};
+
+if READ_ONLY.load(AtomicOrdering::Relaxed) && !cli.command.is_read_only() {
+    eprintln!("{} Read-only mode is on (TODO_READONLY or --read-only); this command would change files.",
+             config.error_prefix());
+    std::process::exit(1);
+}
+
+if todo_list.frozen && !cli.command.allowed_when_frozen() {
+    eprintln!("{} The task list is frozen; run {} to make changes again.", 
+             config.error_prefix(), 
+             "todo unfreeze".cyan());
+    std::process::exit(1);
+}
+
+match cli.command {
+    Commands::Add { description, due, priority, weight, start, estimate, at, tags } => {
+        let id = todo_list.add_task(description.clone(), "add");
+        if let Some(task) = todo_list.get_task_mut(id) {
+            task.due = due;
+            task.priority = priority;
+            task.weight = weight;
+            task.start = start;
+            task.estimate_mins = estimate;
+            task.tags = tags.into_iter().collect();
+        }
+        if let Some(position) = at {
+            todo_list.move_task(id, position);
+        }
+        todo_list.save()?;
+
+        match at.and_then(|_| todo_list.tasks.iter().position(|t| t.id == id)) {
+            Some(index) => {
+                println!("{} Task {} added at position {}: {}", 
+                         config.success_prefix(), 
+                         config.id_style.in_message(id).cyan().bold(),
+                         (index + 1).to_string().cyan().bold(),
+                         description.bright_white());
+            }
+            None => {
+                println!("{} Task {} added: {}", 
+                         config.success_prefix(), 
+                         config.id_style.in_message(id).cyan().bold(),
+                         description.bright_white());
+            }
+        }
+    }
+    Commands::List { todo, done, sort, due_sort_undated_first, due_within, json, extended, max_width, source, include_deferred, summary, pending_total, flag, columns, filter, done_position } => {
+        let sort = SortOrder {
+            specs: sort,
+            undated_first: due_sort_undated_first || config.due_sort_undated_first,
+        };
+
+        let view = if let Some(within) = due_within {
+            ListView::DueWithin(within)
+        } else if todo {
+            ListView::Todo
+        } else if done {
+            ListView::Done
+        } else {
+            ListView::All
+        };
+
+        let pending_total = pending_total || config.pending_total;
+        let options = ListOptions {
+            sort,
+            max_width,
+            source,
+            include_deferred,
+            summary,
+            pending_total,
+            flag,
+            columns,
+            filter,
+            done_position: done_position.unwrap_or(config.done_position),
+        };
+
+        if json && extended {
+            let today = Local::now().date_naive();
+            let views: Vec<TaskView> = todo_list
+                .filtered(view, &options)
+                .into_iter()
+                .map(|task| TaskView::new(task, today))
+                .collect();
+            print_json(&views)?;
+        } else if json {
+            print_json(&todo_list.filtered(view, &options))?;
+        } else {
+            todo_list.list(view, &options, &config);
+        }
+    }
+    Commands::Validate { json } => {
+        // Re-read rather than trusting `todo_list`, which falls back to empty on a parse error.
+        let issues = match TodoList::load() {
+            Ok(list) => list.validate(),
+            Err(e) => vec![Issue {
+                code: "unreadable",
+                message: format!("{} can't be read: {}", TodoList::get_file_path(), e),
+                id: None,
+            }],
+        };
+
+        if json {
+            print_json(&issues)?;
+        } else if issues.is_empty() {
+            println!("{} {} looks good.", 
+                     config.success_prefix(), 
+                     TodoList::get_file_path().cyan());
+        } else {
+            for issue in &issues {
+                eprintln!("{} {} [{}]", 
+                         config.error_prefix(), 
+                         issue.message, 
+                         issue.code.bright_black());
+            }
+        }
+
+        if !issues.is_empty() {
+            std::process::exit(1);
+        }
+    }
+    Commands::Count { todo, done, pending_total, by, json } => {
+        let view = if done {
+            ListView::Done
+        } else if todo || pending_total || config.pending_total {
+            ListView::Todo
+        } else {
+            ListView::All
+        };
+
+        match by {
+            Some(key) if json => {
+                let groups: Vec<serde_json::Value> = todo_list
+                    .count_by(view, key)
+                    .into_iter()
+                    .map(|(name, count)| serde_json::json!({ "key": name, "count": count }))
+                    .collect();
+                println!("{}", serde_json::Value::Array(groups));
+            }
+            Some(key) => {
+                let groups: Vec<(String, usize)> = todo_list
+                    .count_by(view, key)
+                    .into_iter()
+                    .map(|(name, count)| match name {
+                        Some(tag) if key == CountKey::Tag => (format!("#{}", tag), count),
+                        Some(name) => (name, count),
+                        None => ("(none)".to_string(), count),
+                    })
+                    .collect();
+                let width = groups.iter().map(|(name, _)| name.width()).max().unwrap_or(0);
+                for (name, count) in groups {
+                    println!("{:<width$}  {}", name, count.to_string().cyan().bold(), width = width);
+                }
+            }
+            None => println!("{}", todo_list.select(view).len()),
+        }
+    }
+    Commands::Done { id, yes } => {
+        let description = todo_list.tasks.iter().find(|t| t.id == id).map(|t| t.description.clone());
+        let already_done = todo_list.tasks.iter().any(|t| t.id == id && t.status == TaskStatus::Done);
+        let confirmed = match &description {
+            Some(description) if config.confirm_done && !yes && !already_done => {
+                println!("{} {}", 
+                         format!("Task {}:", config.id_style.in_message(id)).bold(), 
+                         description.bright_white());
+                confirm("Mark it as done?")?
+            }
+            _ => true,
+        };
+
+        if !confirmed {
+            println!("{}", "Aborted.".yellow());
+        } else if already_done {
+            // Nothing changes, so don't save or journal a second completion.
+            println!("{} Task {} is already done.", 
+                     config.success_prefix(), 
+                     config.id_style.in_message(id).cyan().bold());
+        } else if todo_list.mark_done(id) {
+            todo_list.save()?;
+            println!("{} Task {} marked as done: {}", 
+                     config.success_prefix(), 
+                     config.id_style.in_message(id).cyan().bold(), 
+                     description.unwrap_or_default().bright_white());
+
+            let task = todo_list.tasks.iter().find(|t| t.id == id);
+            if let (Some(log_path), Some(task)) = (&config.completion_log, task) {
+                if let Err(e) = append_completion_log(log_path, task) {
+                    eprintln!("{} Could not write completion log {}: {}", 
+                             emoji("⚠").yellow().bold(), 
+                             log_path, 
+                             e);
+                }
+            }
+        } else {
+            eprintln!("{} Task {} not found.", 
+                     config.error_prefix(), 
+                     config.id_style.in_message(id).cyan());
+        }
+    }
+    Commands::Undone { id } => {
+        if todo_list.mark_todo(id) {
+            todo_list.save()?;
+            println!("{} Task {} marked as todo.", 
+                     config.success_prefix(), 
+                     config.id_style.in_message(id).cyan().bold());
+        } else {
+            eprintln!("{} Task {} not found.", 
+                     config.error_prefix(), 
+                     config.id_style.in_message(id).cyan());
+        }
+    }
+    Commands::Remove { id, yes } => {
+        let description = todo_list.tasks.iter().find(|t| t.id == id).map(|t| t.description.clone());
+        let prompt = format!("Remove task {} ({})?", 
+                             config.id_style.in_message(id), 
+                             description.as_deref().unwrap_or_default());
+
+        if description.is_some() && !confirm_destructive(&config, yes, &prompt)? {
+            println!("{}", "Aborted.".yellow());
+        } else if todo_list.remove_task(id) {
+            todo_list.save()?;
+            println!("{} Task {} removed.", 
+                     config.success_prefix(), 
+                     config.id_style.in_message(id).cyan().bold());
+        } else {
+            eprintln!("{} Task {} not found.", 
+                     config.error_prefix(), 
+                     config.id_style.in_message(id).cyan());
+        }
+    }
+    Commands::Mark { id, flag } => {
+        match todo_list.set_flag(id, &flag, true) {
+            Some(changed) => {
+                if changed {
+                    todo_list.save()?;
+                }
+                println!("{} Task {} {} {}", 
+                         config.success_prefix(), 
+                         config.id_style.in_message(id).cyan().bold(), 
+                         if changed { "marked" } else { "is already marked" }, 
+                         flag.yellow());
+            }
+            None => {
+                eprintln!("{} Task {} not found.", 
+                         config.error_prefix(), 
+                         config.id_style.in_message(id).cyan());
+            }
+        }
+    }
+    Commands::Unmark { id, flag } => {
+        match todo_list.set_flag(id, &flag, false) {
+            Some(changed) => {
+                if changed {
+                    todo_list.save()?;
+                }
+                println!("{} Task {} {} {}", 
+                         config.success_prefix(), 
+                         config.id_style.in_message(id).cyan().bold(), 
+                         if changed { "unmarked" } else { "wasn't marked" }, 
+                         flag.yellow());
+            }
+            None => {
+                eprintln!("{} Task {} not found.", 
+                         config.error_prefix(), 
+                         config.id_style.in_message(id).cyan());
+            }
+        }
+    }
+    Commands::Estimate { id, minutes } => {
+        match todo_list.get_task_mut(id) {
+            Some(task) => {
+                task.estimate_mins = minutes;
+                todo_list.save()?;
+                match minutes {
+                    Some(minutes) => println!("{} Task {} estimated at {}.", 
+                                              config.success_prefix(), 
+                                              config.id_style.in_message(id).cyan().bold(), 
+                                              format_minutes(u64::from(minutes)).bright_white()),
+                    None => println!("{} Task {} estimate cleared.", 
+                                     config.success_prefix(), 
+                                     config.id_style.in_message(id).cyan().bold()),
+                }
+            }
+            None => {
+                eprintln!("{} Task {} not found.", 
+                         config.error_prefix(), 
+                         config.id_style.in_message(id).cyan());
+            }
+        }
+    }
+    Commands::Eta => {
+        todo_list.show_eta();
+    }
+    Commands::Weight { id, weight } => {
+        if todo_list.set_weight(id, weight) {
+            todo_list.save()?;
+            println!("{} Task {} weight set to {}.", 
+                     config.success_prefix(), 
+                     config.id_style.in_message(id).cyan().bold(),
+                     weight.to_string().bright_white());
+        } else {
+            eprintln!("{} Task {} not found.", 
+                     config.error_prefix(), 
+                     config.id_style.in_message(id).cyan());
+        }
+    }
+    Commands::Stats { weighted } => {
+        todo_list.show_stats(weighted);
+    }
+    Commands::Color { id, color } => {
+        match todo_list.get_task_mut(id) {
+            Some(task) => {
+                task.color = color.clone();
+                todo_list.save()?;
+                match color {
+                    Some(color) => println!("{} Task {} is now {}.", 
+                                            config.success_prefix(), 
+                                            config.id_style.in_message(id).cyan().bold(), 
+                                            color.as_str().color(color.as_str())),
+                    None => println!("{} Task {} color cleared.", 
+                                     config.success_prefix(), 
+                                     config.id_style.in_message(id).cyan().bold()),
+                }
+            }
+            None => {
+                eprintln!("{} Task {} not found.", 
+                         config.error_prefix(), 
+                         config.id_style.in_message(id).cyan());
+            }
+        }
+    }
+    Commands::AutoPrioritize { within } => {
+        let within = within.unwrap_or_else(|| {
+            Duration::days(config.auto_priority_days.unwrap_or(DEFAULT_AUTO_PRIORITY_DAYS))
+        });
+        let raised = todo_list.auto_prioritize(Local::now().date_naive(), within);
+
+        if raised.is_empty() {
+            let message = format!("No pending tasks due within {} day(s) need a higher priority.", within.num_days());
+            println!("{}", message.yellow());
+        } else {
+            todo_list.save()?;
+            for id in &raised {
+                if let Some(task) = todo_list.tasks.iter().find(|t| t.id == *id) {
+                    println!("  {} {} {}", 
+                             config.id_style.in_message(*id).cyan(), 
+                             Priority::High.marker(), 
+                             task.description);
+                }
+            }
+            println!("{} Raised {} task(s) to high priority.", 
+                     config.success_prefix(), 
+                     raised.len().to_string().cyan().bold());
+        }
+    }
+    Commands::Tag { action: TagAction::Color { list: true, .. } } => {
+        if config.tag_colors.is_empty() {
+            println!("{}", "No tag colors yet! Set one with: todo tag color <tag> <color>".yellow());
+        } else {
+            println!("\n{}\n", "Tag Colors:".bold().bright_blue());
+            for (tag, color) in &config.tag_colors {
+                let swatch = match color.parse::<colored::Color>() {
+                    Ok(c) => color.as_str().color(c),
+                    Err(_) => color.as_str().normal(),
+                };
+                println!("  #{:<15} {}", tag, swatch);
+            }
+            println!();
+        }
+    }
+    Commands::Tag { action: TagAction::Color { tag, color, .. } } => {
+        let tag = tag.unwrap_or_default();
+        let mut tag_colors = Config::load()?.tag_colors;
+
+        match color {
+            Some(color) => {
+                tag_colors.insert(tag.clone(), color.clone());
+                Config::save_tag_colors(&tag_colors)?;
+                println!("{} Tasks tagged #{} are now {}.", 
+                         config.success_prefix(), 
+                         tag, 
+                         color.as_str().color(color.as_str()));
+            }
+            None if tag_colors.remove(&tag).is_some() => {
+                Config::save_tag_colors(&tag_colors)?;
+                println!("{} Color for #{} cleared.", 
+                         config.success_prefix(), 
+                         tag);
+            }
+            None => {
+                println!("{}", format!("#{} has no color set.", tag).yellow());
+            }
+        }
+    }
+    Commands::Reset { tag, yes } => {
+        let done = todo_list.tasks
+            .iter()
+            .filter(|t| t.status == TaskStatus::Done && t.tags.contains(&tag))
+            .count();
+
+        if done == 0 {
+            println!("{}", format!("No done tasks tagged #{}.", tag).yellow());
+        } else if yes || confirm(&format!("Reset {} done task(s) tagged #{} to todo?", done, tag))? {
+            let count = todo_list.reset_tagged(&tag);
+            todo_list.save()?;
+            println!("{} Reset {} task(s) tagged #{}.", 
+                     config.success_prefix(), 
+                     count.to_string().cyan().bold(),
+                     tag);
+        } else {
+            println!("{}", "Aborted.".yellow());
+        }
+    }
+    Commands::RescheduleAll { by, yes } => {
+        let dated = todo_list.tasks
+            .iter()
+            .filter(|t| t.status == TaskStatus::Todo && t.due.is_some())
+            .count();
+
+        if dated == 0 {
+            println!("{}", "No pending tasks with due dates.".yellow());
+        } else if yes || confirm(&format!("Shift {} due date(s) by {} day(s)?", dated, by.num_days()))? {
+            let Some(count) = todo_list.reschedule_all(by) else {
+                eprintln!("{} Shifting by {} day(s) would move a due date out of range; nothing was changed.", 
+                         config.error_prefix(), 
+                         by.num_days());
+                std::process::exit(1);
+            };
+            todo_list.save()?;
+            println!("{} Rescheduled {} task(s) by {} day(s).", 
+                     config.success_prefix(), 
+                     count.to_string().cyan().bold(),
+                     by.num_days());
+        } else {
+            println!("{}", "Aborted.".yellow());
+        }
+    }
+    Commands::Move { id, position } => {
+        if todo_list.move_task(id, position) {
+            todo_list.save()?;
+            println!("{} Task {} moved to position {}.", 
+                     config.success_prefix(), 
+                     config.id_style.in_message(id).cyan().bold(),
+                     position.clamp(1, todo_list.tasks.len()));
+        } else {
+            eprintln!("{} Task {} not found.", 
+                     config.error_prefix(), 
+                     config.id_style.in_message(id).cyan());
+        }
+    }
+    Commands::Top { id } => {
+        if todo_list.move_task(id, 1) {
+            todo_list.save()?;
+            println!("{} Task {} moved to the top.", 
+                     config.success_prefix(), 
+                     config.id_style.in_message(id).cyan().bold());
+        } else {
+            eprintln!("{} Task {} not found.", 
+                     config.error_prefix(), 
+                     config.id_style.in_message(id).cyan());
+        }
+    }
+    Commands::Bottom { id } => {
+        if todo_list.move_task(id, usize::MAX) {
+            todo_list.save()?;
+            println!("{} Task {} moved to the bottom.", 
+                     config.success_prefix(), 
+                     config.id_style.in_message(id).cyan().bold());
+        } else {
+            eprintln!("{} Task {} not found.", 
+                     config.error_prefix(), 
+                     config.id_style.in_message(id).cyan());
+        }
+    }
+    Commands::Edit { id, description, interactive } => {
+        let id = if interactive || id.is_none() {
+            let pending: Vec<&Task> = todo_list.tasks
+                .iter()
+                .filter(|t| t.status == TaskStatus::Todo)
+                .collect();
+
+            match pick_task(&pending) {
+                Ok(Some(id)) => Some(id),
+                Ok(None) => {
+                    println!("{}", "Aborted.".yellow());
+                    None
+                }
+                Err(e) => {
+                    eprintln!("{} {}", 
+                             config.error_prefix(), 
+                             e);
+                    std::process::exit(1);
+                }
+            }
+        } else {
+            id
+        };
+
+        if let Some(id) = id {
+            let description = match description {
+                Some(description) => description,
+                None => {
+                    let current = todo_list.tasks
+                        .iter()
+                        .find(|t| t.id == id)
+                        .map(|t| t.description.clone())
+                        .unwrap_or_default();
+                    println!("{} {}", "Current:".bold(), current.bright_white());
+                    print!("{} ", "New description:".bold());
+                    io::stdout().flush()?;
+
+                    let mut line = String::new();
+                    io::stdin().read_line(&mut line)?;
+                    line.trim().to_string()
+                }
+            };
+
+            if description.is_empty() {
+                eprintln!("{} Description can't be empty.", 
+                         config.error_prefix());
+            } else if todo_list.edit_task(id, description.clone()) {
+                todo_list.save()?;
+                println!("{} Task {} updated: {}", 
+                         config.success_prefix(), 
+                         config.id_style.in_message(id).cyan().bold(),
+                         description.bright_white());
+            } else {
+                eprintln!("{} Task {} not found.", 
+                         config.error_prefix(), 
+                         config.id_style.in_message(id).cyan());
+            }
+        }
+    }
+    Commands::Rename { id, description } => {
+        if todo_list.rename_task(id, description.clone()) {
+            todo_list.save()?;
+            println!("{} Task {} renamed to: {}", 
+                     config.success_prefix(), 
+                     config.id_style.in_message(id).cyan().bold(),
+                     description.bright_white());
+        } else {
+            eprintln!("{} Task {} not found.", 
+                     config.error_prefix(), 
+                     config.id_style.in_message(id).cyan());
+        }
+    }
+    Commands::Show { id } => {
+        match todo_list.tasks.iter().find(|t| t.id == id) {
+            Some(task) => task.display_details(&config),
+            None => {
+                eprintln!("{} Task {} not found.", 
+                         config.error_prefix(), 
+                         config.id_style.in_message(id).cyan());
+            }
+        }
+    }
+    #[cfg(feature = "clipboard")]
+    Commands::Copy { id, format } => {
+        match todo_list.tasks.iter().find(|t| t.id == id) {
+            Some(task) => match copy_to_clipboard(task, format) {
+                Ok(text) => {
+                    println!("{} Copied to clipboard: {}", 
+                             config.success_prefix(), 
+                             text.bright_white());
+                }
+                Err(e) => {
+                    eprintln!("{} Clipboard unavailable: {}", 
+                             config.error_prefix(), 
+                             e);
+                }
+            },
+            None => {
+                eprintln!("{} Task {} not found.", 
+                         config.error_prefix(), 
+                         config.id_style.in_message(id).cyan());
+            }
+        }
+    }
+    Commands::Clear { older_than, newer_than, yes } => {
+        let before = todo_list.tasks.clone();
+        let count = todo_list.clear_done(older_than, newer_than);
+
+        if count > 0 && !confirm_destructive(&config, yes, &format!("Clear {} completed task(s)?", count))? {
+            todo_list.tasks = before;
+            println!("{}", "Aborted.".yellow());
+        } else {
+            todo_list.save()?;
+            println!("{} Cleared {} completed task(s).", 
+                     config.success_prefix(), 
+                     count.to_string().cyan().bold());
+        }
+    }
+    #[cfg(feature = "schema")]
+    Commands::Schema => {
+        let schema = serde_json::to_string_pretty(&task_file_schema())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        println!("{}", schema);
+    }
+    Commands::Cat => {
+        let path = TodoList::get_file_path();
+
+        match fs::read(&path) {
+            Ok(bytes) => io::stdout().write_all(&bytes)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                eprintln!("{} {} doesn't exist yet.", 
+                         config.error_prefix(), 
+                         path);
+                std::process::exit(1);
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Commands::Freeze => {
+        todo_list.set_frozen(true)?;
+        println!("{} Task list frozen. Reading and exporting still work.", config.success_prefix());
+    }
+    Commands::Unfreeze => {
+        todo_list.set_frozen(false)?;
+        println!("{} Task list unfrozen.", config.success_prefix());
+    }
+    Commands::Compact => {
+        let count = TodoList::compact()?;
+        println!("{} Compacted {} event(s) into {}.", 
+                 config.success_prefix(), 
+                 count.to_string().cyan().bold(),
+                 TodoList::get_file_path());
+    }
+    Commands::Dedupe { merge, yes } => {
+        let groups = todo_list.duplicate_groups();
+
+        if groups.is_empty() {
+            println!("{}", "No duplicate tasks found.".green().bold());
+        } else {
+            println!("\n{}\n", "Duplicate Tasks:".bold().bright_yellow());
+            for ids in &groups {
+                let ids_text: Vec<String> = ids.iter().map(|id| config.id_style.in_message(*id)).collect();
+                let description = todo_list.tasks
+                    .iter()
+                    .find(|t| t.id == ids[0])
+                    .map(|t| t.description.as_str())
+                    .unwrap_or_default();
+                println!("  {} {}", ids_text.join(", ").bright_cyan(), description.bright_white());
+            }
+            println!();
+
+            let extra: usize = groups.iter().map(|ids| ids.len() - 1).sum();
+            if merge {
+                if yes || confirm(&format!("Merge and remove {} duplicate task(s)?", extra))? {
+                    let count = todo_list.merge_duplicates();
+                    todo_list.save()?;
+                    println!("{} Merged {} duplicate task(s).", 
+                             config.success_prefix(), 
+                             count.to_string().cyan().bold());
+                } else {
+                    println!("{}", "Aborted.".yellow());
+                }
+            }
+        }
+    }
+    Commands::Watch { interval, bell } => {
+        let mut previous_overdue: Option<BTreeSet<usize>> = None;
+
+        loop {
+            let todo_list = TodoList::load().unwrap_or_else(|_| TodoList::new());
+            let today = Local::now().date_naive();
+
+            let overdue: BTreeSet<usize> = todo_list.tasks
+                .iter()
+                .filter(|t| t.is_overdue(today))
+                .map(|t| t.id)
+                .collect();
+
+            print!("\x1b[2J\x1b[H");
+            println!("{}", format!("Watching {} (every {}s, Ctrl-C to stop)", 
+                                   TodoList::get_file_path(), 
+                                   interval).bright_black());
+            todo_list.list(ListView::Todo, &ListOptions::default(), &config);
+
+            // Only ring on transitions, not for tasks that were already overdue.
+            let newly_overdue = previous_overdue
+                .as_ref()
+                .is_some_and(|previous| overdue.difference(previous).next().is_some());
+            if bell && newly_overdue {
+                print!("\x07");
+            }
+            io::stdout().flush()?;
+
+            previous_overdue = Some(overdue);
+            std::thread::sleep(std::time::Duration::from_secs(interval));
+        }
+    }
+    Commands::Import { file, input_format, merge_strategy } => {
+        let from_stdin = file.as_deref().is_none_or(|f| f == "-");
+
+        let contents = if from_stdin {
+            io::read_to_string(io::stdin())?
+        } else {
+            fs::read_to_string(file.as_deref().unwrap_or_default())?
+        };
+
+        let format = input_format
+            .or_else(|| file.as_deref().and_then(InputFormat::from_path))
+            .unwrap_or(InputFormat::Text);
+
+        let (tasks, errors) = parse_import(&contents, format);
+        for error in &errors {
+            eprintln!("{} Skipped {}", 
+                     emoji("⚠").yellow().bold(), 
+                     error);
+        }
+
+        let report = todo_list.import_tasks(tasks, merge_strategy);
+        if !report.added.is_empty() || !report.replaced.is_empty() {
+            todo_list.save()?;
+        }
+        println!("{} Imported {} task(s).", 
+                 config.success_prefix(), 
+                 report.added.len().to_string().cyan().bold());
+        if merge_strategy == MergeStrategy::SkipDuplicates {
+            println!("  {} duplicate(s) skipped", report.skipped.to_string().cyan());
+        }
+        if merge_strategy == MergeStrategy::Replace {
+            println!("  {} existing task(s) replaced", report.replaced.len().to_string().cyan());
+        }
+    }
+    Commands::Backup { action: None } => {
+        match TodoList::backup() {
+            Ok(path) => {
+                println!("{} Backed up to {}", 
+                         config.success_prefix(), 
+                         path.display().to_string().cyan().bold());
+            }
+            Err(e) => {
+                eprintln!("{} Backup failed: {}", 
+                         config.error_prefix(), 
+                         e);
+                std::process::exit(1);
+            }
+        }
+    }
+    Commands::Backup { action: Some(BackupAction::List) } => {
+        let backups = TodoList::list_backups()?;
+
+        if backups.is_empty() {
+            println!("{}", "No backups yet! Create one with: todo backup".yellow());
+        } else {
+            println!("\n{}\n", "Backups:".bold().bright_blue());
+            for name in backups {
+                println!("  {}", name);
+            }
+            println!();
+        }
+    }
+    Commands::Backup { action: Some(BackupAction::Restore { file, yes }) } => {
+        let prompt = format!("Replace {} with {}?", TodoList::get_file_path(), file);
+        if !confirm_destructive(&config, yes, &prompt)? {
+            println!("{}", "Aborted.".yellow());
+            return Ok(());
+        }
+
+        match TodoList::restore(&file) {
+            Ok(path) => {
+                println!("{} Restored tasks from {}", 
+                         config.success_prefix(), 
+                         path.display().to_string().cyan().bold());
+            }
+            Err(e) => {
+                eprintln!("{} Could not restore {}: {}", 
+                         config.error_prefix(), 
+                         file, 
+                         e);
+                std::process::exit(1);
+            }
+        }
+    }
+    Commands::Export { format, out, since_file, reset, append } => {
+        if append && format == ExportFormat::Json {
+            eprintln!("{} {} can't be combined with {}: two arrays in one file isn't valid JSON. Use {} instead.", 
+                     config.error_prefix(), 
+                     "--append".cyan(), 
+                     "--format json".cyan(), 
+                     "--format jsonl".cyan());
+            std::process::exit(1);
+        }
+
+        let started_at = Local::now();
+        let state = match &since_file {
+            Some(path) if !reset => ExportState::load(path)?,
+            _ => ExportState::default(),
+        };
+
+        let tasks: Vec<&Task> = todo_list.tasks.iter().filter(|t| state.is_new(t)).collect();
+        let contents = export_tasks(&tasks, format)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        match out {
+            Some(path) if append => {
+                let mut file = fs::OpenOptions::new().create(true).append(true).open(&path)?;
+                let is_empty = file.metadata()?.len() == 0;
+                let contents = match format {
+                    ExportFormat::Markdown => {
+                        let separator = if is_empty { "" } else { "\n" };
+                        format!("{}## {}\n\n{}", separator, started_at.format("%Y-%m-%d %H:%M"), contents)
+                    }
+                    // The header row only goes at the top of the file.
+                    ExportFormat::Tsv if !is_empty => contents.split_once('\n').map_or("", |(_, rows)| rows).to_string(),
+                    _ => contents,
+                };
+                file.write_all(contents.as_bytes())?;
+                println!("{} Appended {} task(s) to {}", 
+                         config.success_prefix(), 
+                         tasks.len().to_string().cyan().bold(), 
+                         path.cyan());
+            }
+            Some(path) => {
+                fs::write(&path, contents)?;
+                println!("{} Exported {} task(s) to {}", 
+                         config.success_prefix(), 
+                         tasks.len().to_string().cyan().bold(), 
+                         path.cyan());
+            }
+            None => print!("{}", contents),
+        }
+
+        if let Some(path) = since_file {
+            let max_id = todo_list.tasks.iter().map(|t| t.id).max().unwrap_or(0);
+            ExportState { exported_at: Some(started_at), max_id: max_id.max(state.max_id) }.save(&path)?;
+        }
+    }
+    Commands::Search { query, regex, ignore_case, json, limit, page } => {
+        let (limit, page) = (limit.map(|l| l as usize), page as usize);
+
+        match todo_list.search(&query, regex, ignore_case) {
+            Ok(matches) if json => {
+                match limit {
+                    Some(limit) => print_json(paginate(&matches, limit, page).0)?,
+                    None => print_json(&matches)?,
+                }
+            }
+            Ok(matches) if matches.is_empty() => {
+                println!("{}", format!("No tasks match \"{}\".", query).yellow());
+            }
+            Ok(matches) => {
+                let (shown, pages) = paginate(&matches, limit.unwrap_or(matches.len()), page);
+
+                if shown.is_empty() {
+                    println!("{}", format!("Page {} is past the end ({} page(s)).", page, pages).yellow());
+                } else {
+                    println!("\n{}\n", "Search Results:".bold().bright_blue());
+                    for task in shown {
+                        task.display(&config);
+                    }
+                    if limit.is_some() {
+                        let footer = format!("page {}/{} · {} match(es)", page, pages, matches.len());
+                        println!("\n{}", footer.bright_black());
+                    }
+                    println!();
+                }
+            }
+            Err(e) => {
+                eprintln!("{} Invalid pattern: {}", 
+                         config.error_prefix(), 
+                         e);
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn list_of(descriptions: &[&str]) -> TodoList {
+        let mut todo_list = TodoList::new();
+        for description in descriptions {
+            todo_list.add_task(description.to_string(), "add");
+        }
+        todo_list
+    }
+
+    fn ids(tasks: &[&Task]) -> Vec<usize> {
+        tasks.iter().map(|t| t.id).collect()
+    }
+
+    fn sorted_ids(todo_list: &TodoList, specs: &[&str], undated_first: bool) -> Vec<usize> {
+        let order = SortOrder {
+            specs: specs.iter().map(|spec| parse_sort_spec(spec).unwrap()).collect(),
+            undated_first,
+        };
+        let mut tasks: Vec<&Task> = todo_list.tasks.iter().collect();
+        sort_tasks(&mut tasks, &order);
+        ids(&tasks)
+    }
+
+    #[test]
+    fn sort_by_priority_then_due() {
+        let mut todo_list = list_of(&["low", "high later", "none", "high sooner"]);
+        for (id, priority, due) in [
+            (1, Some(Priority::Low), None),
+            (2, Some(Priority::High), Some(date(2026, 3, 20))),
+            (3, None, Some(date(2026, 3, 1))),
+            (4, Some(Priority::High), Some(date(2026, 3, 10))),
+        ] {
+            let task = todo_list.get_task_mut(id).unwrap();
+            task.priority = priority;
+            task.due = due;
+        }
+
+        assert_eq!(sorted_ids(&todo_list, &["priority", "due"], false), vec![4, 2, 1, 3]);
+        assert_eq!(sorted_ids(&todo_list, &["priority", "-due"], false), vec![2, 4, 1, 3]);
+        // Unprioritized tasks stay last in both directions.
+        assert_eq!(sorted_ids(&todo_list, &["-priority", "due"], false), vec![1, 4, 2, 3]);
+    }
+
+    #[test]
+    fn undated_placement_survives_reversing_due() {
+        let mut todo_list = list_of(&["undated", "later", "sooner"]);
+        todo_list.get_task_mut(2).unwrap().due = Some(date(2026, 3, 20));
+        todo_list.get_task_mut(3).unwrap().due = Some(date(2026, 3, 10));
+
+        assert_eq!(compare_due(None, Some(date(2026, 3, 10)), false), Ordering::Greater);
+        assert_eq!(compare_due(None, Some(date(2026, 3, 10)), true), Ordering::Less);
+
+        assert_eq!(sorted_ids(&todo_list, &["due"], false), vec![3, 2, 1]);
+        assert_eq!(sorted_ids(&todo_list, &["due"], true), vec![1, 3, 2]);
+        assert_eq!(sorted_ids(&todo_list, &["-due"], false), vec![2, 3, 1]);
+        assert_eq!(sorted_ids(&todo_list, &["-due"], true), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn wrap_words_respects_fixed_width() {
+        let text = "Write the quarterly report and send it to everyone on the finance team";
+        let lines = wrap_words(text, 20, 30);
+
+        assert_eq!(lines[0], "Write the quarterly");
+        assert!(lines[1..].iter().all(|line| line.width() <= 30));
+        assert_eq!(lines.join(" "), text);
+    }
+
+    #[test]
+    fn wrap_words_breaks_words_wider_than_the_line() {
+        let text = "四半期報告書を作成して財務チームの全員に送信する";
+        let lines = wrap_words(text, 30, 30);
+
+        assert_eq!(lines.len(), 2);
+        assert!(lines.iter().all(|line| line.width() <= 30));
+        assert_eq!(lines.concat(), text);
+    }
+
+    #[test]
+    fn v1_save_drops_v2_fields() {
+        let mut todo_list = list_of(&["tagged", "plain"]);
+        todo_list.frozen = true;
+        let task = todo_list.get_task_mut(1).unwrap();
+        task.due = Some(date(2026, 3, 10));
+        task.priority = Some(Priority::High);
+        task.tags.insert("work".to_string());
+        task.mark_done();
+
+        let value = todo_list.to_versioned_json(1).unwrap();
+
+        assert!(value.get("frozen").is_none());
+        assert_eq!(value["version"], 1);
+        for task in value["tasks"].as_array().unwrap() {
+            let keys: Vec<&str> = task.as_object().unwrap().keys().map(String::as_str).collect();
+            let mut expected = FORMAT_FIELDS[0].1.to_vec();
+            expected.sort();
+            assert_eq!(keys, expected);
+        }
+    }
+
+    #[test]
+    fn each_version_keeps_only_its_fields() {
+        let mut todo_list = list_of(&["estimated"]);
+        todo_list.frozen = true;
+        let task = todo_list.get_task_mut(1).unwrap();
+        task.tags.insert("work".to_string());
+        task.estimate_mins = Some(30);
+
+        let v2 = todo_list.to_versioned_json(2).unwrap();
+        assert!(v2["tasks"][0].get("source").is_some());
+        assert!(v2["tasks"][0].get("created_at").is_none());
+
+        let v8 = todo_list.to_versioned_json(8).unwrap();
+        assert!(v8["tasks"][0].get("tags").is_some());
+        assert!(v8.get("frozen").is_none());
+
+        let current = todo_list.to_versioned_json(FORMAT_VERSION).unwrap();
+        assert_eq!(current, {
+            let mut value = serde_json::to_value(&todo_list).unwrap();
+            value["version"] = serde_json::json!(FORMAT_VERSION);
+            value
+        });
+    }
+
+    #[test]
+    fn tsv_export_escapes_tabs() {
+        let todo_list = list_of(&["Pack\tsocks"]);
+        let tasks: Vec<&Task> = todo_list.tasks.iter().collect();
+
+        let out = export_tasks(&tasks, ExportFormat::Tsv).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+
+        assert_eq!(lines[0], TSV_COLUMNS.join("\t"));
+        assert_eq!(lines.len(), 2);
+        let row: Vec<&str> = lines[1].split('\t').collect();
+        assert_eq!(row.len(), TSV_COLUMNS.len());
+        assert_eq!(row[1], "Pack\\tsocks");
+    }
+
+    fn import_into_seeded(strategy: MergeStrategy) -> (TodoList, ImportReport) {
+        let mut todo_list = list_of(&["Buy milk", "Walk dog"]);
+        let incoming = vec![Task::new(0, " buy MILK ".to_string()), Task::new(0, "Call mom".to_string())];
+        let report = todo_list.import_tasks(incoming, strategy);
+        (todo_list, report)
+    }
+
+    #[test]
+    fn import_new_ids_keeps_duplicates() {
+        let (todo_list, report) = import_into_seeded(MergeStrategy::NewIds);
+
+        assert_eq!(report.added, vec![3, 4]);
+        assert_eq!((report.skipped, report.replaced.len()), (0, 0));
+        assert_eq!(todo_list.tasks.iter().map(|t| t.id).collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+        assert_eq!(todo_list.next_id, 5);
+    }
+
+    #[test]
+    fn import_skip_duplicates_leaves_existing_task() {
+        let (todo_list, report) = import_into_seeded(MergeStrategy::SkipDuplicates);
+
+        assert_eq!(report.added, vec![3]);
+        assert_eq!((report.skipped, report.replaced.len()), (1, 0));
+        assert_eq!(todo_list.tasks.iter().map(|t| t.id).collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(todo_list.tasks[0].description, "Buy milk");
+        assert_eq!(todo_list.tasks[2].description, "Call mom");
+    }
+
+    #[test]
+    fn import_replace_keeps_the_existing_id() {
+        let mut todo_list = list_of(&["Buy milk", "Walk dog"]);
+        let created_at = todo_list.tasks[0].created_at;
+        let existing = todo_list.get_task_mut(1).unwrap();
+        existing.priority = Some(Priority::High);
+        existing.due = Some(date(2026, 3, 10));
+        existing.tags.insert("errands".to_string());
+        existing.flags.insert("starred".to_string());
+
+        let mut incoming = Task::new(0, " buy MILK ".to_string());
+        incoming.due = Some(date(2026, 3, 12));
+        let report = todo_list.import_tasks(vec![incoming, Task::new(0, "Call mom".to_string())], MergeStrategy::Replace);
+
+        assert_eq!(report.added, vec![3]);
+        assert_eq!(report.replaced, vec![1]);
+        assert_eq!(report.skipped, 0);
+        assert_eq!(todo_list.tasks.iter().map(|t| t.id).collect::<Vec<_>>(), vec![1, 2, 3]);
+
+        let replaced = &todo_list.tasks[0];
+        assert_eq!(replaced.description, " buy MILK ");
+        assert_eq!(replaced.source.as_deref(), Some("import"));
+        assert_eq!(replaced.due, Some(date(2026, 3, 12)));
+        assert_eq!(replaced.created_at, created_at);
+        assert_eq!(replaced.priority, Some(Priority::High));
+        assert!(replaced.tags.contains("errands"));
+        assert!(replaced.flags.contains("starred"));
+    }
+
+    #[test]
+    fn replaying_a_compacted_log_again_adds_nothing() {
+        let mut todo_list = TodoList::new();
+        let mut log = String::new();
+        todo_list.add_task("one".to_string(), "add");
+        log += &serde_json::to_string(&Event::Add { task: todo_list.tasks[0].clone() }).unwrap();
+        todo_list.mark_done(1);
+        log += "\n";
+        log += &serde_json::to_string(&Event::Update { task: todo_list.tasks[0].clone() }).unwrap();
+        log += "\n";
+
+        // As if a compaction saved the snapshot but died before removing the rotated log.
+        let mut snapshot = TodoList::new();
+        snapshot.replay_log(&log).unwrap();
+        snapshot.replay_log(&log).unwrap();
+
+        assert_eq!(snapshot.tasks.len(), 1);
+        assert_eq!(snapshot.tasks[0].status, TaskStatus::Done);
+        assert_eq!(snapshot.next_id, 2);
+    }
+
+    #[test]
+    fn due_within_includes_both_edges() {
+        let today = date(2026, 3, 10);
+        let mut todo_list = list_of(&["yesterday", "today", "at limit", "past limit", "undated"]);
+        for (id, due) in [(1, date(2026, 3, 9)), (2, today), (3, date(2026, 3, 17)), (4, date(2026, 3, 18))] {
+            todo_list.get_task_mut(id).unwrap().due = Some(due);
+        }
+
+        assert_eq!(ids(&todo_list.due_within(today, Duration::days(7))), vec![2, 3]);
+        assert_eq!(ids(&todo_list.due_within(today, Duration::days(0))), vec![2]);
+        assert_eq!(ids(&todo_list.due_within(today, parse_duration("99999999d").unwrap())), vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn oversized_durations_are_rejected() {
+        assert!(parse_duration("999999999999999d").is_err());
+        assert!(parse_date("99999999d").is_err());
+        assert_eq!(parse_date("+2w").unwrap(), Local::now().date_naive() + Duration::weeks(2));
+    }
 }